@@ -0,0 +1,236 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{collections::HashMap, fmt, marker::PhantomData};
+
+use codec::{Decode, Encode};
+use sc_client_api::AuxStore;
+use sc_consensus::BlockImportParams;
+use sc_executor::NativeExecutionDispatch;
+use sp_runtime::traits::Block as BlockT;
+
+use crate::{Client, InnerClient};
+
+#[cfg(test)]
+#[path = "auxiliary_tests.rs"]
+mod tests;
+
+type Migration = Box<dyn Fn(Vec<u8>) -> Result<Vec<u8>, String> + Send + Sync>;
+
+/// Error returned by [`AuxStorage`]
+#[derive(Debug)]
+pub enum AuxError {
+    /// Client error
+    Client(sp_blockchain::Error),
+    /// Stored value could not be decoded
+    Decode(codec::Error),
+    /// Stored value has a newer schema version than supported
+    UnsupportedVersion { stored: u32, current: u32 },
+    /// No migration hook registered for schema version `from`
+    MissingMigration { from: u32 },
+    /// Migration hook for schema version `from` failed
+    Migration { from: u32, reason: String },
+}
+
+impl fmt::Display for AuxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuxError::Client(err) => write!(f, "client error: {}", err),
+            AuxError::Decode(err) => write!(f, "failed to decode stored value: {}", err),
+            AuxError::UnsupportedVersion { stored, current } => write!(
+                f,
+                "stored schema version {} is newer than supported version {}",
+                stored, current
+            ),
+            AuxError::MissingMigration { from } => {
+                write!(f, "no migration from schema version {}", from)
+            }
+            AuxError::Migration { from, reason } => {
+                write!(
+                    f,
+                    "migration from schema version {} failed: {}",
+                    from, reason
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for AuxError {}
+
+impl From<sp_blockchain::Error> for AuxError {
+    fn from(err: sp_blockchain::Error) -> Self {
+        AuxError::Client(err)
+    }
+}
+
+impl From<codec::Error> for AuxError {
+    fn from(err: codec::Error) -> Self {
+        AuxError::Decode(err)
+    }
+}
+
+/// Typed and versioned values of type `T` in the aux storage of a client.
+///
+/// Values are SCALE-encoded together with the schema version under keys derived
+/// from a namespace and a name. Values stored with an older schema version are migrated by the
+/// registered migration hooks, one version at a time, when they are loaded.
+pub struct AuxStorage<T> {
+    namespace: Vec<u8>,
+    version: u32,
+    migrations: HashMap<u32, Migration>,
+    _value: PhantomData<T>,
+}
+
+impl<T> AuxStorage<T>
+where
+    T: Encode + Decode,
+{
+    pub fn new(namespace: &[u8], version: u32) -> Self {
+        AuxStorage {
+            namespace: namespace.to_vec(),
+            version,
+            migrations: HashMap::new(),
+            _value: PhantomData,
+        }
+    }
+
+    /// Migrate encoded values from schema version `from` to version `from + 1`
+    pub fn migration<F>(mut self, from: u32, hook: F) -> Self
+    where
+        F: Fn(Vec<u8>) -> Result<Vec<u8>, String> + Send + Sync + 'static,
+    {
+        self.migrations.insert(from, Box::new(hook));
+        self
+    }
+
+    /// Return the namespaced aux storage key for `name`.
+    ///
+    /// The key is the SCALE-encoded pair of namespace and `name`, so keys of different
+    /// namespaces never collide, whatever bytes the namespace or `name` contain.
+    pub fn key(&self, name: &[u8]) -> Vec<u8> {
+        (&self.namespace, name).encode()
+    }
+
+    /// Return the aux storage key and the encoded `value` for `name`
+    pub fn entry(&self, name: &[u8], value: &T) -> (Vec<u8>, Vec<u8>) {
+        (self.key(name), (self.version, value.encode()).encode())
+    }
+
+    /// Load the value for `name`, migrating it if it has been stored with an older
+    /// schema version. Migrated values are written back.
+    pub fn load<A>(&self, store: &A, name: &[u8]) -> Result<Option<T>, AuxError>
+    where
+        A: AuxStore,
+    {
+        let raw = match store.get_aux(&self.key(name))? {
+            Some(raw) => raw,
+            None => return Ok(None),
+        };
+
+        let (stored, mut data) = <(u32, Vec<u8>)>::decode(&mut raw.as_slice())?;
+
+        if stored > self.version {
+            return Err(AuxError::UnsupportedVersion {
+                stored,
+                current: self.version,
+            });
+        }
+
+        for from in stored..self.version {
+            let hook = self
+                .migrations
+                .get(&from)
+                .ok_or(AuxError::MissingMigration { from })?;
+
+            data = hook(data).map_err(|reason| AuxError::Migration { from, reason })?;
+        }
+
+        let value = T::decode(&mut data.as_slice())?;
+
+        if stored < self.version {
+            self.store(store, name, &value)?;
+        }
+
+        Ok(Some(value))
+    }
+
+    /// Store `value` for `name`
+    pub fn store<A>(&self, store: &A, name: &[u8], value: &T) -> Result<(), AuxError>
+    where
+        A: AuxStore,
+    {
+        let (key, value) = self.entry(name, value);
+        store.insert_aux(&[(key.as_slice(), value.as_slice())], &[])?;
+        Ok(())
+    }
+
+    /// Delete the value for `name`
+    pub fn delete<A>(&self, store: &A, name: &[u8]) -> Result<(), AuxError>
+    where
+        A: AuxStore,
+    {
+        store.insert_aux(&[], &[self.key(name).as_slice()])?;
+        Ok(())
+    }
+
+    /// Store `value` for `name` atomically together with the import of `block`
+    pub fn store_on_import<B, Tx>(
+        &self,
+        block: &mut BlockImportParams<B, Tx>,
+        name: &[u8],
+        value: &T,
+    ) where
+        B: BlockT,
+    {
+        let (key, value) = self.entry(name, value);
+        block.auxiliary.push((key, Some(value)));
+    }
+
+    /// Delete the value for `name` atomically together with the import of `block`
+    pub fn delete_on_import<B, Tx>(&self, block: &mut BlockImportParams<B, Tx>, name: &[u8])
+    where
+        B: BlockT,
+    {
+        block.auxiliary.push((self.key(name), None));
+    }
+}
+
+impl<B, D, RA> AuxStore for Client<B, D, RA>
+where
+    B: BlockT,
+    D: NativeExecutionDispatch + 'static,
+    RA: Send + Sync,
+    InnerClient<B, D, RA>: AuxStore,
+{
+    fn insert_aux<
+        'a,
+        'b: 'a,
+        'c: 'a,
+        I: IntoIterator<Item = &'a (&'c [u8], &'c [u8])>,
+        D2: IntoIterator<Item = &'a &'b [u8]>,
+    >(
+        &self,
+        insert: I,
+        delete: D2,
+    ) -> sp_blockchain::Result<()> {
+        self.inner.insert_aux(insert, delete)
+    }
+
+    fn get_aux(&self, key: &[u8]) -> sp_blockchain::Result<Option<Vec<u8>>> {
+        self.inner.get_aux(key)
+    }
+}
@@ -0,0 +1,220 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use codec::{Decode, Encode};
+use sc_client_api::AuxStore;
+use sc_consensus::BlockImport;
+use sp_consensus::BlockOrigin;
+use sp_runtime::traits::Header as HeaderT;
+
+use super::{AuxError, AuxStorage};
+use crate::Client;
+
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+struct VoterStateV1 {
+    round: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+struct VoterState {
+    round: u64,
+    set_id: u64,
+}
+
+#[test]
+fn store_and_load() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let storage = AuxStorage::<VoterState>::new(b"vegan", 1);
+
+    let state = VoterState {
+        round: 3,
+        set_id: 1,
+    };
+
+    assert!(storage.load(&client, b"voter").unwrap().is_none());
+
+    storage.store(&client, b"voter", &state).unwrap();
+
+    assert_eq!(
+        Some(state.clone()),
+        storage.load(&client, b"voter").unwrap()
+    );
+    assert!(client.get_aux(&storage.key(b"voter")).unwrap().is_some());
+
+    // namespaces are isolated
+    let other = AuxStorage::<VoterState>::new(b"simplex", 1);
+
+    assert!(other.load(&client, b"voter").unwrap().is_none());
+
+    // keys are prefix-free, even if namespace or name contain separators
+    let nested = AuxStorage::<VoterState>::new(b"vegan:vo", 1);
+
+    assert_ne!(storage.key(b"vo:ter"), nested.key(b"ter"));
+
+    storage.delete(&client, b"voter").unwrap();
+
+    assert!(storage.load(&client, b"voter").unwrap().is_none());
+}
+
+#[test]
+fn migrate() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+
+    AuxStorage::<VoterStateV1>::new(b"vegan", 1)
+        .store(&client, b"voter", &VoterStateV1 { round: 7 })
+        .unwrap();
+
+    let storage = AuxStorage::<VoterState>::new(b"vegan", 3)
+        .migration(1, |data| {
+            let v1 = VoterStateV1::decode(&mut data.as_slice()).map_err(|e| e.to_string())?;
+            Ok((v1.round, 0u64).encode())
+        })
+        .migration(2, |data| {
+            let (round, _) =
+                <(u64, u64)>::decode(&mut data.as_slice()).map_err(|e| e.to_string())?;
+            Ok(VoterState { round, set_id: 1 }.encode())
+        });
+
+    let expected = VoterState {
+        round: 7,
+        set_id: 1,
+    };
+
+    assert_eq!(
+        Some(expected.clone()),
+        storage.load(&client, b"voter").unwrap()
+    );
+
+    // migrated value has been written back with the current version
+    let raw = client.get_aux(&storage.key(b"voter")).unwrap().unwrap();
+
+    assert_eq!(storage.entry(b"voter", &expected).1, raw);
+}
+
+#[test]
+fn version_errors() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+
+    AuxStorage::<VoterState>::new(b"vegan", 2)
+        .store(
+            &client,
+            b"voter",
+            &VoterState {
+                round: 1,
+                set_id: 1,
+            },
+        )
+        .unwrap();
+
+    let older = AuxStorage::<VoterState>::new(b"vegan", 1);
+
+    assert!(matches!(
+        older.load(&client, b"voter"),
+        Err(AuxError::UnsupportedVersion {
+            stored: 2,
+            current: 1
+        })
+    ));
+
+    let newer = AuxStorage::<VoterState>::new(b"vegan", 3);
+
+    assert!(matches!(
+        newer.load(&client, b"voter"),
+        Err(AuxError::MissingMigration { from: 2 })
+    ));
+
+    assert_eq!(
+        "no migration from schema version 2",
+        newer.load(&client, b"voter").unwrap_err().to_string()
+    );
+
+    let failing = newer.migration(2, |_| Err("broken".to_string()));
+
+    assert!(matches!(
+        failing.load(&client, b"voter"),
+        Err(AuxError::Migration { from: 2, .. })
+    ));
+}
+
+#[tokio::test]
+async fn store_on_import() {
+    sp_tracing::try_init_simple();
+
+    let mut client = Client::new();
+    let storage = AuxStorage::<VoterState>::new(b"vegan", 1);
+
+    storage
+        .store(
+            &client,
+            b"stale",
+            &VoterState {
+                round: 1,
+                set_id: 1,
+            },
+        )
+        .unwrap();
+
+    let mut params = client.new_import_params(BlockOrigin::Own);
+
+    let state = VoterState {
+        round: 2,
+        set_id: 1,
+    };
+
+    storage.store_on_import(&mut params, b"voter", &state);
+    storage.delete_on_import(&mut params, b"stale");
+
+    assert!(storage.load(&client, b"voter").unwrap().is_none());
+
+    client.import_block(params).await.unwrap();
+
+    assert_eq!(Some(state), storage.load(&client, b"voter").unwrap());
+    assert!(storage.load(&client, b"stale").unwrap().is_none());
+}
+
+#[tokio::test]
+async fn store_on_failed_import() {
+    sp_tracing::try_init_simple();
+
+    let mut client = Client::new();
+    let storage = AuxStorage::<VoterState>::new(b"vegan", 1);
+
+    let mut params = client.new_import_params(BlockOrigin::Own);
+
+    // executing the block fails, so the client rejects the import
+    params.header.set_state_root(Default::default());
+
+    storage.store_on_import(
+        &mut params,
+        b"voter",
+        &VoterState {
+            round: 2,
+            set_id: 1,
+        },
+    );
+
+    assert!(client.import_block(params).await.is_err());
+
+    // aux values are only written together with the block
+    assert_eq!(0, client.info().best_number);
+    assert!(storage.load(&client, b"voter").unwrap().is_none());
+}
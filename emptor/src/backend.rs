@@ -14,29 +14,38 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-#![allow(dead_code)]
-
+use codec::Encode;
 use sc_client_api::{Backend, BlockImportOperation, NewBlockState};
-use sp_core::{storage::StateVersion, H256};
+use sp_blockchain::Backend as _;
+use sp_core::storage::StateVersion;
 use sp_runtime::{
-    testing::{Digest, ExtrinsicWrapper, Header},
-    traits::{BlakeTwo256, Hash},
+    testing::ExtrinsicWrapper,
+    traits::{Block as BlockT, Hash as HashT, HashFor, Header as HeaderT, NumberFor, Zero},
+    Digest,
 };
-use sp_state_machine::IndexOperation;
+use sp_state_machine::{Backend as StateBackend, IndexOperation};
 
 #[cfg(test)]
 #[path = "backend_tests.rs"]
 mod tests;
 
-type Block = sp_runtime::testing::Block<ExtrinsicWrapper<u64>>;
+/// Header-only block type for raw backend tests
+pub type Block = sp_runtime::testing::Block<ExtrinsicWrapper<u64>>;
 
-pub fn insert_header(
-    backend: &sc_client_db::Backend<Block>,
-    number: u64,
-    parent_hash: H256,
+/// Insert a block header with an empty body on top of `parent_hash`.
+///
+/// Note that the helpers in this module work with any block type. Besides a raw backend
+/// for [`Block`], the backend of a [`crate::Client`] can be used as well.
+pub fn insert_header<B>(
+    backend: &sc_client_db::Backend<B>,
+    number: NumberFor<B>,
+    parent_hash: B::Hash,
     changes: Option<Vec<(Vec<u8>, Vec<u8>)>>,
-    extrinsics_root: H256,
-) -> H256 {
+    extrinsics_root: B::Hash,
+) -> B::Hash
+where
+    B: BlockT,
+{
     insert_block(
         backend,
         number,
@@ -48,35 +57,64 @@ pub fn insert_header(
     )
 }
 
-pub fn insert_block(
-    backend: &sc_client_db::Backend<Block>,
-    number: u64,
-    parent_hash: H256,
-    _changes: Option<Vec<(Vec<u8>, Vec<u8>)>>,
-    extrinsics_root: H256,
-    body: Vec<ExtrinsicWrapper<u64>>,
+/// Insert a block with `body` on top of `parent_hash`.
+///
+/// The storage `changes` are applied on top of the parent state and the resulting
+/// trie root becomes the block's `state_root`. The state of the inserted block
+/// can be read back using [`sc_client_api::Backend::state_at`].
+pub fn insert_block<B>(
+    backend: &sc_client_db::Backend<B>,
+    number: NumberFor<B>,
+    parent_hash: B::Hash,
+    changes: Option<Vec<(Vec<u8>, Vec<u8>)>>,
+    extrinsics_root: B::Hash,
+    body: Vec<B::Extrinsic>,
     transaction_idx: Option<Vec<IndexOperation>>,
-) -> H256 {
-    let digest = Digest::default();
-
-    let header = Header {
-        parent_hash,
-        number,
-        state_root: BlakeTwo256::trie_root(Vec::new(), StateVersion::default()),
-        extrinsics_root,
-        digest,
-    };
-
-    let hash = header.hash();
-
+) -> B::Hash
+where
+    B: BlockT,
+{
     let mut op = backend
         .begin_operation()
         .expect("begin block insert operation failed");
 
+    // the genesis block is built on top of the empty state
+    let parent_state = if number.is_zero() {
+        Default::default()
+    } else {
+        parent_hash
+    };
+
     backend
-        .begin_state_operation(&mut op, hash)
+        .begin_state_operation(&mut op, parent_state)
         .expect("note state transition failed");
 
+    let changes = changes.unwrap_or_default();
+
+    let (state_root, transaction) = op
+        .state()
+        .expect("parent state not available")
+        .expect("parent state not set")
+        .storage_root(
+            changes
+                .iter()
+                .map(|(k, v)| (k.as_slice(), Some(v.as_slice()))),
+            StateVersion::V1,
+        );
+
+    op.update_db_storage(transaction)
+        .expect("update block storage failed");
+
+    let header = B::Header::new(
+        number,
+        extrinsics_root,
+        state_root,
+        parent_hash,
+        Digest::default(),
+    );
+
+    let hash = header.hash();
+
     op.set_block_data(header, Some(body), None, None, NewBlockState::Best)
         .expect("append block data failed");
 
@@ -89,3 +127,67 @@ pub fn insert_block(
 
     hash
 }
+
+/// Return an index operation for the trailing `size` bytes of the encoded extrinsic at
+/// position `extrinsic` in `body`, together with the hash of the indexed data.
+pub fn index_insert<B>(
+    body: &[B::Extrinsic],
+    extrinsic: u32,
+    size: u32,
+) -> (IndexOperation, B::Hash)
+where
+    B: BlockT,
+{
+    let encoded = body[extrinsic as usize].encode();
+
+    assert!(
+        size as usize <= encoded.len(),
+        "indexed size exceeds extrinsic size"
+    );
+
+    let hash = <HashFor<B> as HashT>::hash(&encoded[encoded.len() - size as usize..]);
+
+    let op = IndexOperation::Insert {
+        extrinsic,
+        hash: hash.as_ref().to_vec(),
+        size,
+    };
+
+    (op, hash)
+}
+
+/// Return an index operation renewing the indexed data with `hash` by the extrinsic at
+/// position `extrinsic` of the block body.
+pub fn index_renew<B>(extrinsic: u32, hash: B::Hash) -> IndexOperation
+where
+    B: BlockT,
+{
+    IndexOperation::Renew {
+        extrinsic,
+        hash: hash.as_ref().to_vec(),
+    }
+}
+
+/// Return the indexed transaction data with `hash`, if it is still stored.
+pub fn indexed_transaction<B>(backend: &sc_client_db::Backend<B>, hash: B::Hash) -> Option<Vec<u8>>
+where
+    B: BlockT,
+{
+    backend
+        .blockchain()
+        .indexed_transaction(hash)
+        .expect("indexed transaction query failed")
+}
+
+/// Return the indexed transaction data of the body of block `hash`.
+///
+/// Return `None`, if the block body is unknown or has been pruned.
+pub fn indexed_body<B>(backend: &sc_client_db::Backend<B>, hash: B::Hash) -> Option<Vec<Vec<u8>>>
+where
+    B: BlockT,
+{
+    backend
+        .blockchain()
+        .block_indexed_body(hash)
+        .expect("indexed body query failed")
+}
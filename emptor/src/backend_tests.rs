@@ -14,11 +14,20 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+use codec::Encode;
 use sc_client_api::Backend;
+use sc_client_db::BlocksPruning;
 use sp_blockchain::{Backend as ChainBackend, HeaderBackend};
-use sp_runtime::{generic::BlockId, ConsensusEngineId, Justification, Justifications};
+use sp_runtime::{
+    generic::BlockId, testing::ExtrinsicWrapper, traits::Header, ConsensusEngineId, Justification,
+    Justifications,
+};
+use sp_state_machine::Backend as StateBackend;
 
-use super::{insert_header, Block};
+use super::{
+    index_insert, index_renew, indexed_body, indexed_transaction, insert_block, insert_header,
+    Block,
+};
 
 #[test]
 fn insert_headers() {
@@ -45,6 +54,114 @@ fn insert_headers() {
     assert_eq!(9, backend.blockchain().info().best_number);
 }
 
+#[test]
+fn insert_state_changes() {
+    let backend = sc_client_db::Backend::<Block>::new_test(10, 10);
+
+    let b_0 = insert_header(
+        &backend,
+        0,
+        Default::default(),
+        Some(vec![(b"key_0".to_vec(), b"value_0".to_vec())]),
+        Default::default(),
+    );
+
+    let b_1 = insert_header(
+        &backend,
+        1,
+        b_0,
+        Some(vec![(b"key_1".to_vec(), b"value_1".to_vec())]),
+        Default::default(),
+    );
+
+    let state = backend.state_at(b_0).unwrap();
+
+    assert_eq!(Some(b"value_0".to_vec()), state.storage(b"key_0").unwrap());
+    assert_eq!(None, state.storage(b"key_1").unwrap());
+
+    let state = backend.state_at(b_1).unwrap();
+
+    assert_eq!(Some(b"value_0".to_vec()), state.storage(b"key_0").unwrap());
+    assert_eq!(Some(b"value_1".to_vec()), state.storage(b"key_1").unwrap());
+
+    let header = backend.blockchain().header(b_1).unwrap().unwrap();
+
+    assert_eq!(
+        *header.state_root(),
+        state.storage_root(std::iter::empty(), Default::default()).0
+    );
+}
+
+#[test]
+fn state_pruning() {
+    let backend = sc_client_db::Backend::<Block>::new_test(2, 0);
+
+    let mut hashes = Vec::new();
+    let mut parent = Default::default();
+
+    for i in 0..5u64 {
+        parent = insert_header(
+            &backend,
+            i,
+            parent,
+            Some(vec![(i.to_le_bytes().to_vec(), vec![i as u8])]),
+            Default::default(),
+        );
+
+        hashes.push(parent);
+    }
+
+    for hash in hashes.iter().skip(1) {
+        backend.finalize_block(BlockId::Hash(*hash), None).unwrap();
+    }
+
+    // only the latest states are kept, older states got pruned
+    assert!(backend.have_state_at(hashes[4], 4));
+    assert!(!backend.have_state_at(hashes[1], 1));
+
+    let state = backend.state_at(hashes[4]).unwrap();
+
+    for i in 0..5u64 {
+        assert_eq!(
+            Some(vec![i as u8]),
+            state.storage(&i.to_le_bytes()).unwrap()
+        );
+    }
+}
+
+#[test]
+fn state_after_finality() {
+    let backend = sc_client_db::Backend::<Block>::new_test(10, 10);
+
+    let b_0 = insert_header(&backend, 0, Default::default(), None, Default::default());
+
+    let b_1_1 = insert_header(
+        &backend,
+        1,
+        b_0,
+        Some(vec![(b"key".to_vec(), b"fork_1".to_vec())]),
+        Default::default(),
+    );
+
+    let b_1_2 = insert_header(
+        &backend,
+        1,
+        b_0,
+        Some(vec![(b"key".to_vec(), b"fork_2".to_vec())]),
+        [1; 32].into(),
+    );
+
+    backend.finalize_block(BlockId::Hash(b_1_2), None).unwrap();
+
+    assert_eq!(
+        Some(b"fork_2".to_vec()),
+        backend.state_at(b_1_2).unwrap().storage(b"key").unwrap()
+    );
+
+    // state of the discarded fork is gone after canonicalization
+    assert!(!backend.have_state_at(b_1_1, 1));
+}
+
 #[test]
 fn prunning_leaves() {
     let backend = sc_client_db::Backend::<Block>::new_test(10, 10);
@@ -153,3 +270,106 @@ fn append_justifications() {
             .unwrap(),
     );
 }
+
+#[test]
+fn indexed_transactions() {
+    let backend =
+        sc_client_db::Backend::<Block>::new_test_with_tx_storage(BlocksPruning::KeepAll, 0);
+
+    let b_0 = insert_header(&backend, 0, Default::default(), None, Default::default());
+
+    let body = vec![ExtrinsicWrapper::from(1u64), ExtrinsicWrapper::from(2u64)];
+    let (op, hash) = index_insert::<Block>(&body, 1, 4);
+
+    let b_1 = insert_block(
+        &backend,
+        1,
+        b_0,
+        None,
+        Default::default(),
+        body.clone(),
+        Some(vec![op]),
+    );
+
+    let encoded = body[1].encode();
+    let data = encoded[encoded.len() - 4..].to_vec();
+
+    assert_eq!(Some(data.clone()), indexed_transaction(&backend, hash));
+    assert_eq!(Some(vec![data]), indexed_body(&backend, b_1));
+    assert_eq!(Some(vec![]), indexed_body(&backend, b_0));
+    assert_eq!(None, indexed_transaction(&backend, Default::default()));
+}
+
+#[test]
+fn indexed_transactions_pruning() {
+    let backend =
+        sc_client_db::Backend::<Block>::new_test_with_tx_storage(BlocksPruning::Some(2), 0);
+
+    let mut hashes = vec![insert_header(
+        &backend,
+        0,
+        Default::default(),
+        None,
+        Default::default(),
+    )];
+
+    let body = vec![ExtrinsicWrapper::from(1u64), ExtrinsicWrapper::from(2u64)];
+    let (op_1, hash_1) = index_insert::<Block>(&body, 0, 4);
+    let (op_2, hash_2) = index_insert::<Block>(&body, 1, 4);
+
+    hashes.push(insert_block(
+        &backend,
+        1,
+        hashes[0],
+        None,
+        Default::default(),
+        body,
+        Some(vec![op_1, op_2]),
+    ));
+
+    hashes.push(insert_header(
+        &backend,
+        2,
+        hashes[1],
+        None,
+        Default::default(),
+    ));
+
+    // renew the first indexed transaction only
+    hashes.push(insert_block(
+        &backend,
+        3,
+        hashes[2],
+        None,
+        Default::default(),
+        vec![ExtrinsicWrapper::from(3u64)],
+        Some(vec![index_renew::<Block>(0, hash_1)]),
+    ));
+
+    for i in 4..6 {
+        hashes.push(insert_header(
+            &backend,
+            i,
+            hashes[i as usize - 1],
+            None,
+            Default::default(),
+        ));
+    }
+
+    for hash in hashes.iter().take(4).skip(1) {
+        backend.finalize_block(BlockId::Hash(*hash), None).unwrap();
+    }
+
+    // body of block #1 got pruned, only the renewed transaction is still available
+    assert_eq!(None, indexed_body(&backend, hashes[1]));
+    assert!(indexed_transaction(&backend, hash_1).is_some());
+    assert_eq!(None, indexed_transaction(&backend, hash_2));
+
+    for hash in hashes.iter().skip(4) {
+        backend.finalize_block(BlockId::Hash(*hash), None).unwrap();
+    }
+
+    // body of block #3 got pruned as well
+    assert_eq!(None, indexed_body(&backend, hashes[3]));
+    assert_eq!(None, indexed_transaction(&backend, hash_1));
+}
@@ -0,0 +1,189 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{path::PathBuf, sync::Arc};
+
+use sc_client_db::{BlocksPruning, DatabaseSettings, DatabaseSource, PruningMode};
+use sp_database::MemDb;
+use substrate_test_runtime_client::Backend;
+use tempfile::TempDir;
+
+use crate::{Client, Genesis};
+
+#[cfg(test)]
+#[path = "builder_tests.rs"]
+mod tests;
+
+/// Database backing a [`Client`]
+///
+/// For on-disk databases a temporary directory is used, if no path is given. The
+/// temporary directory is removed once the last client using it has been dropped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Database {
+    /// In-memory database
+    InMemory,
+    /// RocksDB database at the given path
+    RocksDb(Option<PathBuf>),
+    /// ParityDB database at the given path
+    ParityDb(Option<PathBuf>),
+}
+
+/// Builder for a [`Client`] with configurable pruning, database and genesis
+#[derive(Clone)]
+pub struct ClientBuilder {
+    genesis: Genesis,
+    state_pruning: PruningMode,
+    blocks_pruning: BlocksPruning,
+    canonicalization_delay: u64,
+    database: Database,
+    // in-memory database, kept for reopening the client
+    memory: Option<MemDb>,
+    // temporary database directory, kept for reopening the client
+    tmp: Option<Arc<TempDir>>,
+    // whether the database has been opened before
+    opened: bool,
+}
+
+impl Default for ClientBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ClientBuilder {
+    /// Return a builder for an in-memory client with effectively no pruning
+    pub fn new() -> Self {
+        ClientBuilder {
+            genesis: Genesis::default(),
+            state_pruning: PruningMode::blocks_pruning(std::u32::MAX),
+            blocks_pruning: BlocksPruning::Some(std::u32::MAX),
+            canonicalization_delay: std::u64::MAX,
+            database: Database::InMemory,
+            memory: None,
+            tmp: None,
+            opened: false,
+        }
+    }
+
+    /// Return a builder for reopening the database of `client`.
+    ///
+    /// This simulates a node restart. The database is only closed, once all clones of
+    /// `client` and all objects referring to its backend have been dropped. Panics, if
+    /// `client` has not been built by a [`ClientBuilder`].
+    pub fn reopen(client: Client) -> Self {
+        let builder = client
+            .builder
+            .clone()
+            .expect("client not built by a `ClientBuilder`");
+        drop(client);
+        builder
+    }
+
+    /// Set the genesis configuration.
+    ///
+    /// Note that the genesis configuration is ignored, when reopening a database.
+    pub fn genesis(mut self, genesis: Genesis) -> Self {
+        self.genesis = genesis;
+        self
+    }
+
+    /// Set the state pruning mode
+    pub fn state_pruning(mut self, state_pruning: PruningMode) -> Self {
+        self.state_pruning = state_pruning;
+        self
+    }
+
+    /// Set the blocks pruning mode
+    pub fn blocks_pruning(mut self, blocks_pruning: BlocksPruning) -> Self {
+        self.blocks_pruning = blocks_pruning;
+        self
+    }
+
+    /// Set the number of blocks after which non-finalized blocks get canonicalized
+    pub fn canonicalization_delay(mut self, delay: u64) -> Self {
+        self.canonicalization_delay = delay;
+        self
+    }
+
+    /// Set the database backing the client
+    pub fn database(mut self, database: Database) -> Self {
+        self.database = database;
+        self.memory = None;
+        self.tmp = None;
+        self.opened = false;
+        self
+    }
+
+    /// Return the database path, if the client is backed by an on-disk database
+    pub fn path(&self) -> Option<PathBuf> {
+        match self.database {
+            Database::RocksDb(ref path) | Database::ParityDb(ref path) => path.clone(),
+            Database::InMemory => None,
+        }
+    }
+
+    /// Build the client
+    pub fn build(mut self) -> Client {
+        let source = self.source();
+
+        let settings = DatabaseSettings {
+            trie_cache_maximum_size: Some(16 * 1024 * 1024),
+            state_pruning: Some(self.state_pruning.clone()),
+            source,
+            blocks_pruning: self.blocks_pruning,
+        };
+
+        let backend = Arc::new(
+            Backend::new(settings, self.canonicalization_delay).expect("failed to open database"),
+        );
+
+        self.opened = true;
+
+        let mut client: Client = Client::with_backend(backend, self.genesis.clone());
+        client.builder = Some(self);
+        client
+    }
+
+    // Return the database source, a database path will be resolved to a temporary
+    // directory, if necessary.
+    fn source(&mut self) -> DatabaseSource {
+        if let Database::RocksDb(None) | Database::ParityDb(None) = self.database {
+            let tmp = tempfile::tempdir().expect("failed to create temporary directory");
+            let path = Some(tmp.path().join("db"));
+
+            self.database = match self.database {
+                Database::RocksDb(_) => Database::RocksDb(path),
+                _ => Database::ParityDb(path),
+            };
+
+            self.tmp = Some(Arc::new(tmp));
+        }
+
+        match self.database {
+            Database::InMemory => DatabaseSource::Custom {
+                db: Arc::new(self.memory.get_or_insert_with(MemDb::default).clone()),
+                require_create_flag: !self.opened,
+            },
+            Database::RocksDb(ref path) => DatabaseSource::RocksDb {
+                path: path.clone().expect("database path resolved above"),
+                cache_size: 128,
+            },
+            Database::ParityDb(ref path) => DatabaseSource::ParityDb {
+                path: path.clone().expect("database path resolved above"),
+            },
+        }
+    }
+}
@@ -0,0 +1,166 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use sc_block_builder::BlockBuilderProvider;
+use sc_client_api::{Backend as _, BlockBackend};
+use sp_consensus::BlockOrigin;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+use substrate_test_runtime_client::prelude::*;
+
+use super::{ClientBuilder, Database};
+use crate::{
+    prelude::{BlocksPruning, PruningMode},
+    Client,
+};
+
+// Import `count` blocks at best block and return their hashes
+async fn import_blocks(client: &mut Client, count: usize) -> Vec<Hash> {
+    let mut hashes = Vec::new();
+
+    for _ in 0..count {
+        let block = client
+            .inner
+            .new_block(Default::default())
+            .unwrap()
+            .build()
+            .unwrap()
+            .block;
+
+        hashes.push(block.hash());
+
+        client.inner.import(BlockOrigin::File, block).await.unwrap();
+    }
+
+    hashes
+}
+
+#[tokio::test]
+async fn state_pruning() {
+    sp_tracing::try_init_simple();
+
+    let mut client = ClientBuilder::new()
+        .state_pruning(PruningMode::blocks_pruning(2))
+        .canonicalization_delay(0)
+        .build();
+
+    let hashes = import_blocks(&mut client, 5).await;
+
+    client
+        .finalize_block(BlockId::Hash(hashes[4]), None, true)
+        .unwrap();
+
+    let backend = client.as_backend();
+
+    assert!(backend.have_state_at(hashes[4], 5));
+    assert!(!backend.have_state_at(hashes[0], 1));
+}
+
+#[tokio::test]
+async fn archive() {
+    sp_tracing::try_init_simple();
+
+    let mut client = ClientBuilder::new()
+        .state_pruning(PruningMode::ArchiveAll)
+        .blocks_pruning(BlocksPruning::KeepAll)
+        .canonicalization_delay(0)
+        .build();
+
+    let hashes = import_blocks(&mut client, 5).await;
+
+    client
+        .finalize_block(BlockId::Hash(hashes[4]), None, true)
+        .unwrap();
+
+    let backend = client.as_backend();
+
+    assert!(hashes
+        .iter()
+        .enumerate()
+        .all(|(n, hash)| backend.have_state_at(*hash, n as u64 + 1)));
+}
+
+#[tokio::test]
+async fn blocks_pruning() {
+    sp_tracing::try_init_simple();
+
+    let mut client = ClientBuilder::new()
+        .blocks_pruning(BlocksPruning::Some(2))
+        .state_pruning(PruningMode::blocks_pruning(2))
+        .canonicalization_delay(0)
+        .build();
+
+    let hashes = import_blocks(&mut client, 5).await;
+
+    client
+        .finalize_block(BlockId::Hash(hashes[4]), None, true)
+        .unwrap();
+
+    assert!(client.as_inner().block_body(hashes[0]).unwrap().is_none());
+    assert!(client.as_inner().block_body(hashes[4]).unwrap().is_some());
+}
+
+#[tokio::test]
+async fn reopen_in_memory() {
+    sp_tracing::try_init_simple();
+
+    let mut client = Client::new();
+
+    let hashes = import_blocks(&mut client, 3).await;
+
+    let client = ClientBuilder::reopen(client).build();
+
+    assert_eq!(hashes[2], client.info().best_hash);
+}
+
+#[tokio::test]
+async fn reopen_rocksdb() {
+    sp_tracing::try_init_simple();
+
+    let mut client = ClientBuilder::new()
+        .database(Database::RocksDb(None))
+        .build();
+
+    assert!(client.builder.as_ref().unwrap().path().is_some());
+
+    let hashes = import_blocks(&mut client, 3).await;
+
+    client
+        .finalize_block(BlockId::Hash(hashes[1]), None, true)
+        .unwrap();
+
+    let client = ClientBuilder::reopen(client).build();
+    let info = client.info();
+
+    assert_eq!(hashes[2], info.best_hash);
+    assert_eq!(hashes[1], info.finalized_hash);
+}
+
+#[tokio::test]
+async fn reopen_paritydb() {
+    sp_tracing::try_init_simple();
+
+    let mut client = ClientBuilder::new()
+        .database(Database::ParityDb(None))
+        .build();
+
+    let hashes = import_blocks(&mut client, 3).await;
+
+    let path = client.builder.as_ref().unwrap().path();
+    let client = ClientBuilder::reopen(client).build();
+
+    assert_eq!(path, client.builder.as_ref().unwrap().path());
+    assert_eq!(hashes[2], client.info().best_hash);
+}
@@ -14,55 +14,226 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use std::sync::Arc;
+use std::{sync::Arc, time::Instant};
 
-use sc_client_api::{backend::Finalizer, BlockBackend};
-use sc_consensus::{BlockCheckParams, BlockImport, BlockImportParams, ImportResult, LongestChain};
+use parking_lot::Mutex;
+use sc_block_builder::{BlockBuilder, BlockBuilderProvider, BuiltBlock};
+use sc_client_api::{
+    backend::{Finalizer, StateBackendFor, TransactionFor},
+    BlockBackend,
+};
+use sc_consensus::{
+    BlockCheckParams, BlockImport, BlockImportParams, ForkChoiceStrategy, ImportResult,
+    LongestChain, StateAction, StorageChanges,
+};
+use sc_executor::{NativeElseWasmExecutor, NativeExecutionDispatch};
+use sc_service::client::LocalCallExecutor;
 use sp_blockchain::Info;
-use sp_runtime::{generic::BlockId, Justification};
-use substrate_test_runtime::Block;
-use substrate_test_runtime_client::{Backend, TestClient, TestClientBuilder, TestClientBuilderExt};
+use sp_consensus::BlockOrigin;
+use sp_inherents::InherentData;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT, Digest, Justification};
+use substrate_test_client::{GenesisInit, TestClientBuilder};
+use substrate_test_runtime::{Block, Hash, RuntimeApi};
+use substrate_test_runtime_client::LocalExecutorDispatch;
 
-use crate::AnyBlockImport;
+use crate::{AnyBlockImport, ClientBuilder, ImportStats, MockClock};
 
 #[cfg(test)]
 #[path = "client_tests.rs"]
 mod tests;
 
-#[derive(Clone)]
-pub struct Client {
-    pub(crate) inner: Arc<TestClient>,
-    pub(crate) backend: Arc<Backend>,
-    pub(crate) chain: LongestChain<substrate_test_runtime_client::Backend, Block>,
+/// Database backend of a [`Client`]
+pub type Backend<B> = sc_client_db::Backend<B>;
+
+/// Substrate client wrapped by a [`Client`]
+pub type InnerClient<B, D, RA> = sc_service::client::Client<
+    Backend<B>,
+    LocalCallExecutor<B, Backend<B>, NativeElseWasmExecutor<D>>,
+    B,
+    RA,
+>;
+
+/// A client for block type `B`, native executor dispatch `D` and runtime API `RA`.
+///
+/// By default, the client uses the substrate test runtime. Block building and most
+/// helpers, like [`crate::TransactionPool`], [`crate::ReorgObserver`],
+/// [`crate::MockRuntimeClient`], [`crate::ImportQueueHarness`] and the
+/// [`sp_consensus::SelectChain`] strategies, work with the default client only.
+pub struct Client<B = Block, D = LocalExecutorDispatch, RA = RuntimeApi>
+where
+    B: BlockT,
+{
+    pub(crate) inner: Arc<InnerClient<B, D, RA>>,
+    pub(crate) backend: Arc<Backend<B>>,
+    pub(crate) chain: LongestChain<Backend<B>, B>,
+    pub(crate) builder: Option<ClientBuilder>,
+    pub(crate) clock: Option<MockClock>,
+    pub(crate) keep_changes: bool,
+    pub(crate) stats: Arc<Mutex<ImportStats>>,
 }
 
 impl Client {
+    /// Return an in-memory client with effectively no pruning.
+    ///
+    /// Use [`ClientBuilder`] for a client with custom pruning or database settings.
     pub fn new() -> Client {
-        let backend = Arc::new(Backend::new_test(std::u32::MAX, std::u64::MAX));
-        let builder = TestClientBuilder::with_backend(backend);
-        let backend = builder.backend();
+        ClientBuilder::new().build()
+    }
 
-        let (client, chain) = builder.build_with_longest_chain();
+    /// Create a new block builder at `parent`.
+    ///
+    /// If the client has a [`MockClock`], the block builder will contain the inherents
+    /// for the current clock time, as far as provided by the runtime.
+    pub fn new_block_at(
+        &self,
+        parent: Hash,
+        inherent_digests: Digest,
+    ) -> sp_blockchain::Result<
+        BlockBuilder<
+            '_,
+            Block,
+            InnerClient<Block, LocalExecutorDispatch, RuntimeApi>,
+            Backend<Block>,
+        >,
+    > {
+        let mut builder = self.inner.new_block_at(parent, inherent_digests, false)?;
 
-        Client {
-            inner: Arc::new(client),
-            backend,
-            chain,
+        if let Some(clock) = &self.clock {
+            let mut data = InherentData::new();
+
+            data.put_data(sp_timestamp::INHERENT_IDENTIFIER, &clock.now())
+                .map_err(|err| sp_blockchain::Error::Application(Box::new(err)))?;
+
+            for xt in builder.create_inherents(data)? {
+                builder.push(xt)?;
+            }
+        }
+
+        Ok(builder)
+    }
+
+    /// Import a block built by a block builder of this client.
+    ///
+    /// If the client keeps storage changes, the changes computed by the block builder are
+    /// applied and the block is not re-executed.
+    pub async fn import_built_block(
+        &self,
+        origin: BlockOrigin,
+        built: BuiltBlock<Block, StateBackendFor<Backend<Block>, Block>>,
+    ) -> Result<ImportResult, sp_consensus::Error> {
+        let mut params = import_params(origin, built.block);
+
+        if self.keep_changes {
+            params.state_action =
+                StateAction::ApplyChanges(StorageChanges::Changes(built.storage_changes));
         }
+
+        let start = Instant::now();
+
+        let res = self.inner.clone().import_block(params).await;
+
+        self.stats.lock().record(start.elapsed(), self.keep_changes);
+
+        res
     }
 }
 
+#[cfg(test)]
+impl Client {
+    // Return import params for a new block on top of the best block, without importing it
+    pub(crate) fn new_import_params<Tx>(
+        &self,
+        origin: BlockOrigin,
+    ) -> BlockImportParams<Block, Tx> {
+        let block = self
+            .new_block_at(self.info().best_hash, Default::default())
+            .expect("failed to create a new block")
+            .build()
+            .expect("failed to build block")
+            .block;
+
+        import_params(origin, block)
+    }
+}
+
+/// Return import params for `block` as coming from `origin`, using the longest chain
+/// fork choice rule.
+pub fn import_params<B, Tx>(origin: BlockOrigin, block: B) -> BlockImportParams<B, Tx>
+where
+    B: BlockT,
+{
+    let (header, body) = block.deconstruct();
+
+    let mut params = BlockImportParams::new(origin, header);
+    params.body = Some(body);
+    params.fork_choice = Some(ForkChoiceStrategy::LongestChain);
+
+    params
+}
+
 impl Default for Client {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl Client {
+impl<B, D, RA> Clone for Client<B, D, RA>
+where
+    B: BlockT,
+{
+    fn clone(&self) -> Self {
+        Client {
+            inner: self.inner.clone(),
+            backend: self.backend.clone(),
+            chain: self.chain.clone(),
+            builder: self.builder.clone(),
+            clock: self.clock.clone(),
+            keep_changes: self.keep_changes,
+            stats: self.stats.clone(),
+        }
+    }
+}
+
+impl<B, D, RA> Client<B, D, RA>
+where
+    B: BlockT,
+    D: NativeExecutionDispatch + 'static,
+    RA: Send + Sync,
+{
+    /// Return a client backed by `backend`, which will be initialized from `genesis`,
+    /// unless the backend has been initialized before.
+    pub fn with_backend<G>(backend: Arc<Backend<B>>, genesis: G) -> Self
+    where
+        G: GenesisInit,
+    {
+        let mut builder = TestClientBuilder::<
+            B,
+            LocalCallExecutor<B, Backend<B>, NativeElseWasmExecutor<D>>,
+            Backend<B>,
+            G,
+        >::with_backend(backend);
+
+        *builder.genesis_init_mut() = genesis;
+
+        let backend = builder.backend();
+
+        let (client, chain) = builder.build_with_native_executor::<RA, _>(None);
+
+        Client {
+            inner: Arc::new(client),
+            backend,
+            chain,
+            builder: None,
+            clock: None,
+            keep_changes: false,
+            stats: Default::default(),
+        }
+    }
+
     /// Implementation for [`sc_client_api::backend::Finalizer`]
     pub fn finalize_block(
         &self,
-        id: BlockId<Block>,
+        id: BlockId<B>,
         justification: Option<Justification>,
         notify: bool,
     ) -> sp_blockchain::Result<()> {
@@ -79,52 +250,107 @@ impl Client {
         self.inner.finalize_block(hash, justification, notify)
     }
 
+    /// Use `clock` for timestamp inherents of blocks built by this client
+    pub fn with_clock(mut self, clock: MockClock) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Return the client clock, if any
+    pub fn clock(&self) -> Option<&MockClock> {
+        self.clock.as_ref()
+    }
+
+    /// Keep storage changes on import, see [`Client::import_built_block`].
+    ///
+    /// This applies to [`BlockImport::import_block`] of the client as well, so import
+    /// params carrying precomputed storage changes skip block re-execution.
+    pub fn keep_storage_changes(mut self, keep: bool) -> Self {
+        self.keep_changes = keep;
+        self
+    }
+
+    /// Return block import timing statistics
+    pub fn import_stats(&self) -> ImportStats {
+        self.stats.lock().clone()
+    }
+
+    /// Reset block import timing statistics
+    pub fn reset_import_stats(&self) {
+        *self.stats.lock() = ImportStats::default();
+    }
+
     /// Return a clone of the client as [`crate::AnyBlockImport`]
     pub fn as_block_import(&self) -> AnyBlockImport<Self> {
         AnyBlockImport::new(self.clone())
     }
 
-    /// Return a clone of the inner [`substrate_test_runtime_client::TestClient`]
-    pub fn as_inner(&self) -> Arc<TestClient> {
+    /// Return a clone of the inner [`InnerClient`]
+    pub fn as_inner(&self) -> Arc<InnerClient<B, D, RA>> {
         self.inner.clone()
     }
 
-    /// Return a clone of the client [`substrate_test_runtime_client::Backend`]
-    pub fn as_backend(&self) -> Arc<Backend> {
+    /// Return a clone of the client [`Backend`]
+    pub fn as_backend(&self) -> Arc<Backend<B>> {
         self.backend.clone()
     }
     /// Return client blockchain info
-    pub fn info(&self) -> Info<Block> {
+    pub fn info(&self) -> Info<B> {
         self.inner.chain_info()
     }
 
     /// Return a clone of the client longest chain
-    pub fn chain(&self) -> LongestChain<substrate_test_runtime_client::Backend, Block> {
+    pub fn chain(&self) -> LongestChain<Backend<B>, B> {
         self.chain.clone()
     }
 }
 
 #[async_trait::async_trait]
-impl BlockImport<Block> for Client {
+impl<B, D, RA> BlockImport<B> for Client<B, D, RA>
+where
+    B: BlockT,
+    D: NativeExecutionDispatch + 'static,
+    RA: Send + Sync,
+    Arc<InnerClient<B, D, RA>>: BlockImport<B, Error = sp_consensus::Error> + Send + Sync,
+    Arc<InnerClient<B, D, RA>>: BlockImport<B, Transaction = TransactionFor<Backend<B>, B>>,
+{
     type Error = sp_consensus::Error;
 
-    type Transaction = ();
+    type Transaction = TransactionFor<Backend<B>, B>;
 
     /// Check block preconditions
     async fn check_block(
         &mut self,
-        block: BlockCheckParams<Block>,
+        block: BlockCheckParams<B>,
     ) -> Result<ImportResult, Self::Error> {
         self.inner.check_block(block).await
     }
 
     /// Import a block
+    ///
+    /// Precomputed storage changes are applied if the client keeps storage changes,
+    /// otherwise they are cleared and the block is re-executed.
     async fn import_block(
         &mut self,
-        block: BlockImportParams<Block, Self::Transaction>,
+        block: BlockImportParams<B, Self::Transaction>,
     ) -> Result<ImportResult, Self::Error> {
-        self.inner
-            .import_block(block.clear_storage_changes_and_mutate())
-            .await
+        let block = if self.keep_changes {
+            block
+        } else {
+            block.clear_storage_changes_and_mutate()
+        };
+
+        let with_changes = matches!(
+            block.state_action,
+            StateAction::ApplyChanges(StorageChanges::Changes(_))
+        );
+
+        let start = Instant::now();
+
+        let res = self.inner.import_block(block).await;
+
+        self.stats.lock().record(start.elapsed(), with_changes);
+
+        res
     }
 }
@@ -14,17 +14,23 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use futures::executor;
+use std::{sync::Arc, time::Duration};
+
+use futures::{FutureExt, StreamExt};
 use sc_block_builder::BlockBuilderProvider;
 use sc_client_api::{BlockchainEvents, HeaderBackend};
+use sc_consensus::{BlockImport, ImportResult, StateAction, StorageChanges};
 use sp_consensus::BlockOrigin;
 use sp_runtime::{
-    generic::{Digest, DigestItem},
-    ConsensusEngineId, Justification, Justifications,
+    generic::DigestItem, traits::Block as BlockT, ConsensusEngineId, Justification, Justifications,
 };
+use substrate_test_runtime::RuntimeApi;
 use substrate_test_runtime_client::prelude::*;
 
 use super::Client;
+use crate::{
+    backend::insert_header, import_params, BlockTree, ConsensusBlockBuilder, Genesis, ImportStats,
+};
 
 const ENGINE_ID: ConsensusEngineId = *b"SMPL";
 
@@ -131,20 +137,12 @@ async fn finality_notification() {
 
     let mut client = Client::new();
 
-    let mut finality_stream =
-        executor::block_on_stream(client.inner.finality_notification_stream());
+    let mut finality_stream = client.inner.finality_notification_stream();
 
-    let digest = Digest {
-        logs: vec![DigestItem::Consensus(ENGINE_ID, vec![1, 2, 3])],
-    };
-
-    let block = client
-        .inner
-        .new_block(digest)
-        .unwrap()
-        .build()
-        .unwrap()
-        .block;
+    let block = ConsensusBlockBuilder::new(ENGINE_ID)
+        .consensus(vec![1, 2, 3])
+        .build(&client, client.info().best_hash)
+        .unwrap();
 
     let _ = client
         .inner
@@ -152,9 +150,118 @@ async fn finality_notification() {
         .await;
 
     let import_hash = client.info().best_hash;
-    let finality_notification = finality_stream.next().unwrap();
+
+    client
+        .wait_for_finalized(import_hash, Duration::from_secs(5))
+        .await
+        .unwrap();
+
+    let finality_notification = finality_stream.next().now_or_never().flatten().unwrap();
     assert_eq!(import_hash, finality_notification.hash);
 
     let item = DigestItem::Consensus(ENGINE_ID, vec![1, 2, 3]);
     assert_eq!(item, finality_notification.header.digest.logs[0]);
 }
+
+#[tokio::test]
+async fn with_backend() {
+    sp_tracing::try_init_simple();
+
+    let backend = Arc::new(Backend::new_test(1000, 0));
+
+    let mut client = Client::<Block, LocalExecutorDispatch, RuntimeApi>::with_backend(
+        backend,
+        Genesis::default(),
+    );
+
+    let block = client
+        .inner
+        .new_block(Default::default())
+        .unwrap()
+        .build()
+        .unwrap()
+        .block;
+
+    let _ = client.inner.import(BlockOrigin::File, block).await;
+
+    assert_eq!(1, client.info().best_number);
+}
+
+#[test]
+fn insert_raw_header() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let genesis = client.info().genesis_hash;
+
+    // raw backend helpers work with the client backend as well
+    let hash = insert_header(&*client.as_backend(), 1, genesis, None, Default::default());
+
+    assert_eq!(hash, client.info().best_hash);
+    assert_eq!(Some(hash), client.inner.hash(1).unwrap());
+}
+
+#[tokio::test]
+async fn keep_storage_changes() {
+    sp_tracing::try_init_simple();
+
+    let tree = BlockTree::parse("G-A-B-C; B-D").unwrap();
+
+    let executed = Client::new();
+    tree.build_client(&executed).await;
+
+    let kept = Client::new().keep_storage_changes(true);
+    let labels = tree.build_client(&kept).await;
+
+    let stats = executed.import_stats();
+
+    assert_eq!(stats.imports, 4);
+    assert_eq!(stats.with_changes, 0);
+
+    let stats = kept.import_stats();
+
+    assert_eq!(stats.imports, 4);
+    assert_eq!(stats.with_changes, 4);
+    assert!(stats.mean().is_some());
+
+    // both modes result in the same chain
+    assert_eq!(kept.info().best_hash, labels["C"]);
+    assert_eq!(kept.info().best_hash, executed.info().best_hash);
+
+    kept.reset_import_stats();
+
+    assert_eq!(kept.import_stats(), ImportStats::default());
+}
+
+#[tokio::test]
+async fn block_import_keeps_storage_changes() {
+    sp_tracing::try_init_simple();
+
+    for keep in [false, true] {
+        let mut client = Client::new().keep_storage_changes(keep);
+
+        let built = client
+            .new_block_at(client.info().genesis_hash, Default::default())
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let hash = built.block.hash();
+
+        let mut params = import_params(BlockOrigin::Own, built.block);
+        params.state_action =
+            StateAction::ApplyChanges(StorageChanges::Changes(built.storage_changes));
+
+        let res = BlockImport::import_block(&mut client, params)
+            .await
+            .unwrap();
+
+        assert!(matches!(res, ImportResult::Imported(_)));
+        assert_eq!(hash, client.info().best_hash);
+
+        let stats = client.import_stats();
+
+        assert_eq!(1, stats.imports);
+        assert_eq!(keep as usize, stats.with_changes);
+    }
+}
@@ -0,0 +1,138 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use sp_consensus_slots::Slot;
+use sp_timestamp::Timestamp;
+use tokio::time::Instant;
+
+#[cfg(test)]
+#[path = "clock_tests.rs"]
+mod tests;
+
+/// Default slot duration of a [`MockClock`]
+pub const DEFAULT_SLOT_DURATION: Duration = Duration::from_millis(6000);
+
+/// A controllable clock, shared between a [`crate::Client`] and test code
+///
+/// A manual clock only moves if advanced explicitly. A tokio clock additionally follows
+/// [`tokio::time::Instant`], which makes it advance together with tokio's paused time.
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    // milliseconds since the UNIX epoch, excluding time elapsed since `origin`
+    offset: Arc<AtomicU64>,
+    origin: Option<Instant>,
+    slot_duration: Duration,
+}
+
+impl MockClock {
+    /// Return a manual clock starting at `start` milliseconds
+    pub fn new(start: u64) -> Self {
+        MockClock {
+            offset: Arc::new(AtomicU64::new(start)),
+            origin: None,
+            slot_duration: DEFAULT_SLOT_DURATION,
+        }
+    }
+
+    /// Return a clock starting at `start` milliseconds, which follows tokio time.
+    ///
+    /// Must be called from within a tokio runtime.
+    pub fn tokio(start: u64) -> Self {
+        MockClock {
+            origin: Some(Instant::now()),
+            ..Self::new(start)
+        }
+    }
+
+    pub fn with_slot_duration(mut self, slot_duration: Duration) -> Self {
+        assert!(!slot_duration.is_zero(), "slot duration must not be zero");
+        self.slot_duration = slot_duration;
+        self
+    }
+
+    pub fn slot_duration(&self) -> Duration {
+        self.slot_duration
+    }
+
+    /// Return the current time
+    pub fn now(&self) -> Timestamp {
+        let elapsed = self.origin.map_or(0, |o| o.elapsed().as_millis() as u64);
+        Timestamp::new(self.offset.load(Ordering::SeqCst) + elapsed)
+    }
+
+    /// Set the current time to `now` milliseconds.
+    ///
+    /// Panics, if this would move the clock backwards.
+    pub fn set(&self, now: u64) {
+        let current = *self.now();
+        assert!(now >= current, "clock must not go backwards");
+        self.offset.fetch_add(now - current, Ordering::SeqCst);
+    }
+
+    /// Advance the clock by `duration`
+    pub fn advance(&self, duration: Duration) {
+        self.offset
+            .fetch_add(duration.as_millis() as u64, Ordering::SeqCst);
+    }
+
+    /// Return the current slot
+    pub fn slot(&self) -> Slot {
+        Slot::from(*self.now() / self.slot_duration.as_millis() as u64)
+    }
+
+    /// Return the start time of `slot`
+    pub fn slot_start(&self, slot: Slot) -> Timestamp {
+        Timestamp::new(*slot * self.slot_duration.as_millis() as u64)
+    }
+
+    /// Advance the clock to the start of the slot `n` slots ahead, return that slot
+    pub fn advance_slots(&self, n: u64) -> Slot {
+        let slot = self.slot() + n;
+        self.set(*self.slot_start(slot));
+        slot
+    }
+
+    /// Wait until `slot` has started.
+    ///
+    /// A manual clock is advanced immediately, a tokio clock sleeps using tokio time.
+    pub async fn wait_for_slot(&self, slot: Slot) {
+        let start = *self.slot_start(slot);
+        let now = *self.now();
+
+        if start <= now {
+            return;
+        }
+
+        match self.origin {
+            Some(_) => tokio::time::sleep(Duration::from_millis(start - now)).await,
+            None => self.set(start),
+        }
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
@@ -0,0 +1,144 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::time::Duration;
+
+use codec::Decode;
+use sc_client_api::StorageProvider;
+use sp_consensus_slots::Slot;
+use sp_core::{hashing::twox_128, storage::StorageKey, H256};
+
+use super::MockClock;
+use crate::{Client, TransactionPool};
+
+// Return the timestamp set by the timestamp inherent of block `hash`
+fn timestamp(client: &Client, hash: H256) -> Option<u64> {
+    let key = [twox_128(b"Timestamp"), twox_128(b"Now")].concat();
+
+    client
+        .as_inner()
+        .storage(hash, &StorageKey(key))
+        .unwrap()
+        .map(|data| u64::decode(&mut &data.0[..]).unwrap())
+}
+
+#[test]
+fn manual_clock() {
+    sp_tracing::try_init_simple();
+
+    let clock = MockClock::new(1000);
+    let shared = clock.clone();
+
+    assert_eq!(1000, *clock.now());
+
+    clock.advance(Duration::from_millis(500));
+
+    assert_eq!(1500, *shared.now());
+
+    shared.set(2000);
+
+    assert_eq!(2000, *clock.now());
+}
+
+#[test]
+#[should_panic(expected = "clock must not go backwards")]
+fn manual_clock_backwards() {
+    sp_tracing::try_init_simple();
+
+    let clock = MockClock::new(1000);
+    clock.set(999);
+}
+
+#[test]
+fn slots() {
+    sp_tracing::try_init_simple();
+
+    let clock = MockClock::new(0).with_slot_duration(Duration::from_millis(100));
+
+    assert_eq!(Slot::from(0), clock.slot());
+
+    clock.advance(Duration::from_millis(250));
+
+    assert_eq!(Slot::from(2), clock.slot());
+    assert_eq!(200, *clock.slot_start(Slot::from(2)));
+
+    let slot = clock.advance_slots(3);
+
+    assert_eq!(Slot::from(5), slot);
+    assert_eq!(slot, clock.slot());
+    assert_eq!(500, *clock.now());
+}
+
+#[tokio::test]
+async fn manual_wait_for_slot() {
+    sp_tracing::try_init_simple();
+
+    let clock = MockClock::new(0).with_slot_duration(Duration::from_millis(100));
+
+    clock.wait_for_slot(Slot::from(4)).await;
+
+    assert_eq!(400, *clock.now());
+
+    // waiting for a past slot does not move the clock
+    clock.wait_for_slot(Slot::from(1)).await;
+
+    assert_eq!(400, *clock.now());
+}
+
+#[tokio::test(start_paused = true)]
+async fn tokio_clock() {
+    sp_tracing::try_init_simple();
+
+    let clock = MockClock::tokio(1000).with_slot_duration(Duration::from_millis(100));
+
+    tokio::time::advance(Duration::from_millis(250)).await;
+
+    assert_eq!(1250, *clock.now());
+    assert_eq!(Slot::from(12), clock.slot());
+
+    clock.wait_for_slot(Slot::from(20)).await;
+
+    assert_eq!(2000, *clock.now());
+    assert_eq!(Slot::from(20), clock.slot());
+
+    clock.advance(Duration::from_millis(100));
+
+    assert_eq!(2100, *clock.now());
+}
+
+#[tokio::test]
+async fn client_clock() {
+    sp_tracing::try_init_simple();
+
+    let clock = MockClock::new(1_000_000);
+    let client = Client::new().with_clock(clock.clone());
+
+    assert_eq!(clock.now(), client.clock().unwrap().now());
+
+    let pool = TransactionPool::new(client.clone());
+
+    let a = pool.author_block(None).await;
+
+    assert_eq!(Some(*clock.now()), timestamp(&client, a));
+
+    clock.advance_slots(1);
+
+    let b = pool.author_block(None).await;
+
+    assert_eq!(b, client.info().best_hash);
+    assert_eq!(Some(*clock.now()), timestamp(&client, b));
+    assert_eq!(Some(1_006_000), timestamp(&client, b));
+}
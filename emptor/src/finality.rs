@@ -0,0 +1,228 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{fmt, sync::Arc};
+
+use futures::channel::mpsc::UnboundedReceiver;
+use parking_lot::Mutex;
+use sp_blockchain::{tree_route, HeaderBackend, TreeRoute};
+use sp_core::H256;
+use sp_runtime::{generic::BlockId, traits::NumberFor, ConsensusEngineId, Justification};
+use substrate_test_runtime::Block;
+use tracing::debug;
+
+use crate::{notify::Sinks, Client};
+
+#[cfg(test)]
+#[path = "finality_tests.rs"]
+mod tests;
+
+/// A violated finality rule
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// Block is not above the last finalized block
+    NotIncreasing {
+        finalized: NumberFor<Block>,
+        number: NumberFor<Block>,
+    },
+    /// Block does not descend from the last finalized block
+    NotDescendant { finalized: H256, hash: H256 },
+    /// Justification is not from the configured consensus engine
+    EngineMismatch {
+        expected: ConsensusEngineId,
+        found: ConsensusEngineId,
+    },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::NotIncreasing { finalized, number } => write!(
+                f,
+                "block #{} is not above the last finalized block #{}",
+                number, finalized
+            ),
+            Violation::NotDescendant { finalized, hash } => write!(
+                f,
+                "block {:?} does not descend from the last finalized block {:?}",
+                hash, finalized
+            ),
+            Violation::EngineMismatch { expected, found } => write!(
+                f,
+                "justification from engine {:?}, expected {:?}",
+                String::from_utf8_lossy(found),
+                String::from_utf8_lossy(expected)
+            ),
+        }
+    }
+}
+
+/// Error returned by [`FinalityController::finalize`]
+#[derive(Debug)]
+pub enum FinalityError {
+    /// Finalization would violate a finality rule
+    Violation(Violation),
+    /// Client error
+    Client(sp_blockchain::Error),
+}
+
+impl fmt::Display for FinalityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FinalityError::Violation(violation) => write!(f, "finality violation: {}", violation),
+            FinalityError::Client(err) => write!(f, "client error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for FinalityError {}
+
+impl From<sp_blockchain::Error> for FinalityError {
+    fn from(err: sp_blockchain::Error) -> Self {
+        FinalityError::Client(err)
+    }
+}
+
+/// Audit record of a single finalization
+#[derive(Debug, Clone)]
+pub struct FinalityRecord {
+    pub hash: H256,
+    pub number: NumberFor<Block>,
+    pub justification: Option<Justification>,
+    /// Ancestors finalized implicitly, oldest first
+    pub implicit: Vec<H256>,
+    /// Tree route from the previously finalized block to this block
+    pub route: TreeRoute<Block>,
+}
+
+#[derive(Default)]
+struct Audit {
+    records: Mutex<Vec<FinalityRecord>>,
+    violations: Mutex<Vec<Violation>>,
+    sinks: Sinks<FinalityRecord>,
+}
+
+/// Finalizes blocks of a [`Client`], enforcing and auditing finality rules.
+///
+/// Finalized block numbers have to increase monotonically and each finalized block has
+/// to descend from the previously finalized block. If an engine id is configured,
+/// justifications from other consensus engines are rejected.
+#[derive(Clone)]
+pub struct FinalityController {
+    client: Client,
+    engine_id: Option<ConsensusEngineId>,
+    audit: Arc<Audit>,
+}
+
+impl FinalityController {
+    pub fn new(client: Client) -> Self {
+        FinalityController {
+            client,
+            engine_id: None,
+            audit: Default::default(),
+        }
+    }
+
+    /// Only accept justifications from consensus engine `engine_id`
+    pub fn with_engine_id(mut self, engine_id: ConsensusEngineId) -> Self {
+        self.engine_id = Some(engine_id);
+        self
+    }
+
+    /// Finalize the block with `hash`, if no finality rule is violated
+    pub fn finalize(
+        &self,
+        hash: H256,
+        justification: Option<Justification>,
+    ) -> Result<FinalityRecord, FinalityError> {
+        let info = self.client.info();
+
+        let number = self
+            .client
+            .inner
+            .number(hash)?
+            .ok_or_else(|| sp_blockchain::Error::UnknownBlock(format!("block {}", hash)))?;
+
+        if number <= info.finalized_number {
+            return Err(self.violation(Violation::NotIncreasing {
+                finalized: info.finalized_number,
+                number,
+            }));
+        }
+
+        if let (Some(expected), Some((found, _))) = (self.engine_id, justification.as_ref()) {
+            if expected != *found {
+                return Err(self.violation(Violation::EngineMismatch {
+                    expected,
+                    found: *found,
+                }));
+            }
+        }
+
+        let route = tree_route(&*self.client.inner, info.finalized_hash, hash)?;
+
+        if !route.retracted().is_empty() {
+            return Err(self.violation(Violation::NotDescendant {
+                finalized: info.finalized_hash,
+                hash,
+            }));
+        }
+
+        self.client
+            .finalize_block(BlockId::Hash(hash), justification.clone(), true)?;
+
+        let implicit = route
+            .enacted()
+            .iter()
+            .map(|b| b.hash)
+            .filter(|h| *h != hash)
+            .collect();
+
+        let record = FinalityRecord {
+            hash,
+            number,
+            justification,
+            implicit,
+            route,
+        };
+
+        self.audit.records.lock().push(record.clone());
+        self.audit.sinks.notify(record.clone());
+
+        Ok(record)
+    }
+
+    /// Return all finalizations, in order
+    pub fn log(&self) -> Vec<FinalityRecord> {
+        self.audit.records.lock().clone()
+    }
+
+    /// Return all rejected finalizations, in order
+    pub fn violations(&self) -> Vec<Violation> {
+        self.audit.violations.lock().clone()
+    }
+
+    /// Return a stream of all finalizations from now on
+    pub fn stream(&self) -> UnboundedReceiver<FinalityRecord> {
+        self.audit.sinks.subscribe()
+    }
+
+    fn violation(&self, violation: Violation) -> FinalityError {
+        debug!(target: "emptor", "Finality rule violated: {:?}", violation);
+        self.audit.violations.lock().push(violation.clone());
+        FinalityError::Violation(violation)
+    }
+}
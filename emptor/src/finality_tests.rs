@@ -0,0 +1,142 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use futures::StreamExt;
+use sp_runtime::ConsensusEngineId;
+
+use super::{FinalityController, FinalityError, Violation};
+use crate::{BlockTree, Client};
+
+const ENGINE_ID: ConsensusEngineId = *b"SMPL";
+
+#[tokio::test]
+async fn finalize_with_ancestors() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let labels = BlockTree::parse("G-A-B-C-D; B-X")
+        .unwrap()
+        .build_client(&client)
+        .await;
+
+    let controller = FinalityController::new(client.clone());
+    let mut stream = controller.stream();
+
+    let record = controller.finalize(labels["C"], None).unwrap();
+
+    assert_eq!(3, record.number);
+    assert_eq!(vec![labels["A"], labels["B"]], record.implicit);
+    assert_eq!(labels["G"], record.route.common_block().hash);
+    assert!(record.route.retracted().is_empty());
+
+    assert_eq!(labels["C"], client.info().finalized_hash);
+    assert_eq!(labels["C"], stream.next().await.unwrap().hash);
+
+    controller
+        .finalize(labels["D"], Some((ENGINE_ID, vec![1, 2, 3])))
+        .unwrap();
+
+    let log = controller.log();
+
+    assert_eq!(2, log.len());
+    assert!(log[1].implicit.is_empty());
+    assert_eq!(Some((ENGINE_ID, vec![1, 2, 3])), log[1].justification);
+}
+
+#[tokio::test]
+async fn not_increasing() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let labels = BlockTree::parse("G-A-B")
+        .unwrap()
+        .build_client(&client)
+        .await;
+
+    let controller = FinalityController::new(client.clone());
+
+    controller.finalize(labels["B"], None).unwrap();
+
+    assert!(matches!(
+        controller.finalize(labels["A"], None),
+        Err(FinalityError::Violation(Violation::NotIncreasing {
+            finalized: 2,
+            number: 1
+        }))
+    ));
+
+    assert_eq!(
+        "finality violation: block #2 is not above the last finalized block #2",
+        controller
+            .finalize(labels["B"], None)
+            .unwrap_err()
+            .to_string()
+    );
+
+    assert_eq!(2, controller.violations().len());
+    assert_eq!(labels["B"], client.info().finalized_hash);
+}
+
+#[tokio::test]
+async fn not_descendant() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let labels = BlockTree::parse("G-A-B-C; A-X-Y-Z")
+        .unwrap()
+        .build_client(&client)
+        .await;
+
+    let controller = FinalityController::new(client.clone());
+
+    controller.finalize(labels["B"], None).unwrap();
+
+    let violation = Violation::NotDescendant {
+        finalized: labels["B"],
+        hash: labels["Z"],
+    };
+
+    assert!(matches!(
+        controller.finalize(labels["Z"], None),
+        Err(FinalityError::Violation(ref v)) if *v == violation
+    ));
+
+    assert_eq!(vec![violation], controller.violations());
+    assert_eq!(labels["B"], client.info().finalized_hash);
+}
+
+#[tokio::test]
+async fn engine_mismatch() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let labels = BlockTree::parse("G-A").unwrap().build_client(&client).await;
+
+    let controller = FinalityController::new(client.clone()).with_engine_id(ENGINE_ID);
+
+    assert!(matches!(
+        controller.finalize(labels["A"], Some((*b"BEEF", vec![]))),
+        Err(FinalityError::Violation(Violation::EngineMismatch { .. }))
+    ));
+
+    assert_eq!(0, client.info().finalized_number);
+
+    controller
+        .finalize(labels["A"], Some((ENGINE_ID, vec![])))
+        .unwrap();
+
+    assert_eq!(1, client.info().finalized_number);
+}
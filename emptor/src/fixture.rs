@@ -0,0 +1,284 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::VecDeque,
+    io::{Read, Write},
+    ops::{Bound, RangeBounds},
+};
+
+use codec::{Decode, Encode};
+use sc_client_api::{Backend as _, BlockBackend};
+use sc_consensus::{BlockImport, ImportResult};
+use sp_blockchain::{Backend as _, Error, HeaderBackend};
+use sp_consensus::BlockOrigin;
+use sp_core::bytes;
+use sp_runtime::{
+    generic::BlockId,
+    traits::{Block as BlockT, Header as HeaderT, NumberFor},
+    Justifications,
+};
+use substrate_test_runtime::Block;
+
+use crate::{import_params, Client};
+
+#[cfg(test)]
+#[path = "fixture_tests.rs"]
+mod tests;
+
+/// Magic prefix of SCALE-encoded chain fixtures
+const MAGIC: [u8; 4] = *b"EMPT";
+
+/// Chain fixture format version
+const VERSION: u32 = 1;
+
+/// Encoding of a chain fixture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixtureFormat {
+    /// Magic prefix followed by the SCALE-encoded fixture
+    Scale,
+    /// JSON object containing the format version and a list of hex-encoded blocks
+    Json,
+}
+
+#[derive(Debug, Encode)]
+struct Fixture {
+    version: u32,
+    blocks: Vec<FixtureBlock>,
+}
+
+#[derive(Debug, Encode, Decode)]
+struct FixtureBlock {
+    block: Block,
+    justifications: Option<Justifications>,
+    finalized: bool,
+}
+
+impl Client {
+    /// Export all blocks with a block number in `range` to `writer`.
+    ///
+    /// Blocks on all forks are exported, parents before children. The genesis block
+    /// is never exported. Return the number of exported blocks.
+    pub fn export_blocks<R, W>(
+        &self,
+        range: R,
+        format: FixtureFormat,
+        mut writer: W,
+    ) -> sp_blockchain::Result<usize>
+    where
+        R: RangeBounds<NumberFor<Block>>,
+        W: Write,
+    {
+        let start = match range.start_bound() {
+            Bound::Included(n) => *n,
+            Bound::Excluded(n) => n + 1,
+            Bound::Unbounded => 1,
+        }
+        .max(1);
+
+        let end = match range.end_bound() {
+            Bound::Included(n) => *n,
+            Bound::Excluded(n) => n.saturating_sub(1),
+            Bound::Unbounded => NumberFor::<Block>::MAX,
+        };
+
+        let info = self.info();
+        let blockchain = self.backend.blockchain();
+
+        // all blocks numbered `start`, the canonical one first
+        let mut queue: VecDeque<_> = blockchain.hash(start)?.into_iter().collect();
+
+        for leaf in blockchain.leaves()? {
+            let mut hash = leaf;
+
+            while let Some(header) = blockchain.header(hash)? {
+                if *header.number() <= start {
+                    if *header.number() == start && !queue.contains(&hash) {
+                        queue.push_back(hash);
+                    }
+                    break;
+                }
+
+                hash = *header.parent_hash();
+            }
+        }
+
+        let mut blocks = Vec::new();
+
+        while let Some(hash) = queue.pop_front() {
+            let block = self
+                .inner
+                .block(hash)?
+                .ok_or_else(|| Error::UnknownBlock(format!("block {}", hash)))?;
+
+            let number = *block.block.header().number();
+
+            if number > end {
+                continue;
+            }
+
+            let finalized =
+                number <= info.finalized_number && blockchain.hash(number)? == Some(hash);
+
+            queue.extend(blockchain.children(hash)?);
+
+            blocks.push(FixtureBlock {
+                block: block.block,
+                justifications: block.justifications,
+                finalized,
+            });
+        }
+
+        let count = blocks.len();
+
+        match format {
+            FixtureFormat::Scale => {
+                let fixture = Fixture {
+                    version: VERSION,
+                    blocks,
+                };
+
+                writer.write_all(&MAGIC).map_err(io_error)?;
+                writer.write_all(&fixture.encode()).map_err(io_error)?;
+            }
+            FixtureFormat::Json => {
+                let blocks: Vec<_> = blocks
+                    .iter()
+                    .map(|b| bytes::to_hex(&b.encode(), false))
+                    .collect();
+
+                let json = serde_json::json!({ "version": VERSION, "blocks": blocks });
+
+                serde_json::to_writer_pretty(&mut writer, &json)
+                    .map_err(|e| Error::Backend(e.to_string()))?;
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Import all blocks of a chain fixture read from `reader`.
+    ///
+    /// The fixture format is detected automatically. Blocks are imported through the
+    /// [`BlockImport`] pipeline as coming from `origin`. Blocks which have been finalized
+    /// at export are finalized after all blocks have been imported. Return the number of
+    /// imported blocks, blocks already in chain are skipped.
+    pub async fn import_blocks<R>(
+        &self,
+        mut reader: R,
+        origin: BlockOrigin,
+    ) -> sp_blockchain::Result<usize>
+    where
+        R: Read,
+    {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).map_err(io_error)?;
+
+        let blocks = decode(&data)?;
+
+        let mut import = self.clone();
+        let mut imported = 0;
+        let mut finalized = None;
+
+        for FixtureBlock {
+            block,
+            justifications,
+            finalized: is_finalized,
+        } in blocks
+        {
+            let hash = block.hash();
+            let number = *block.header().number();
+
+            let mut params = import_params(origin, block);
+            params.justifications = justifications;
+
+            match import.import_block(params).await {
+                Ok(ImportResult::Imported(_)) => imported += 1,
+                Ok(ImportResult::AlreadyInChain) => {}
+                Ok(res) => {
+                    return Err(Error::ClientImport(format!(
+                        "import of block {} failed: {:?}",
+                        hash, res
+                    )))
+                }
+                Err(err) => {
+                    return Err(Error::ClientImport(format!(
+                        "import of block {} failed: {}",
+                        hash, err
+                    )))
+                }
+            }
+
+            if is_finalized && finalized.map_or(true, |(n, _)| number > n) {
+                finalized = Some((number, hash));
+            }
+        }
+
+        if let Some((number, hash)) = finalized {
+            if number > self.info().finalized_number {
+                self.finalize_block(BlockId::Hash(hash), None, true)?;
+            }
+        }
+
+        Ok(imported)
+    }
+}
+
+// Decode a chain fixture from `data`.
+//
+// The format version is checked before any block is decoded, since the block layout
+// may change with the version.
+fn decode(data: &[u8]) -> sp_blockchain::Result<Vec<FixtureBlock>> {
+    let codec_error = |e: codec::Error| Error::Backend(format!("invalid chain fixture: {}", e));
+
+    let unsupported =
+        |version: u64| Error::Backend(format!("unsupported chain fixture version: {}", version));
+
+    if let Some(mut data) = data.strip_prefix(&MAGIC[..]) {
+        let version = u32::decode(&mut data).map_err(codec_error)?;
+
+        if version != VERSION {
+            return Err(unsupported(version.into()));
+        }
+
+        return Vec::<FixtureBlock>::decode(&mut data).map_err(codec_error);
+    }
+
+    let json: serde_json::Value = serde_json::from_slice(data)
+        .map_err(|e| Error::Backend(format!("invalid chain fixture: {}", e)))?;
+
+    let version = json["version"].as_u64().unwrap_or_default();
+
+    if version != u64::from(VERSION) {
+        return Err(unsupported(version));
+    }
+
+    json["blocks"]
+        .as_array()
+        .ok_or_else(|| Error::Backend("invalid chain fixture: missing blocks".into()))?
+        .iter()
+        .map(|b| {
+            let b = b.as_str().unwrap_or_default();
+            let b = bytes::from_hex(b)
+                .map_err(|e| Error::Backend(format!("invalid chain fixture: {}", e)))?;
+            FixtureBlock::decode(&mut &b[..]).map_err(codec_error)
+        })
+        .collect()
+}
+
+fn io_error(e: std::io::Error) -> Error {
+    Error::Backend(e.to_string())
+}
@@ -0,0 +1,202 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use sc_client_api::{Backend as _, BlockBackend};
+use sp_blockchain::Backend as _;
+use sp_consensus::BlockOrigin;
+
+use super::FixtureFormat;
+use crate::{BlockTree, Client};
+
+const TREE: &str = "G-A-B-C#-D-E-F; B-X-Y-Z; D-P-Q";
+
+#[tokio::test]
+async fn scale_roundtrip() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let labels = BlockTree::parse(TREE).unwrap().build_client(&client).await;
+
+    let mut fixture = Vec::new();
+
+    assert_eq!(
+        11,
+        client
+            .export_blocks(.., FixtureFormat::Scale, &mut fixture)
+            .unwrap()
+    );
+
+    let other = Client::new();
+
+    assert_eq!(
+        11,
+        other
+            .import_blocks(&fixture[..], BlockOrigin::File)
+            .await
+            .unwrap()
+    );
+
+    assert_eq!(client.info(), other.info());
+    assert_eq!(labels["C"], other.info().finalized_hash);
+
+    assert_eq!(
+        client.as_backend().blockchain().leaves().unwrap(),
+        other.as_backend().blockchain().leaves().unwrap()
+    );
+
+    assert_eq!(
+        client.as_inner().justifications(labels["C"]).unwrap(),
+        other.as_inner().justifications(labels["C"]).unwrap()
+    );
+}
+
+#[tokio::test]
+async fn json_roundtrip() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    BlockTree::parse(TREE).unwrap().build_client(&client).await;
+
+    let mut fixture = Vec::new();
+
+    client
+        .export_blocks(.., FixtureFormat::Json, &mut fixture)
+        .unwrap();
+
+    let other = Client::new();
+
+    other
+        .import_blocks(&fixture[..], BlockOrigin::NetworkInitialSync)
+        .await
+        .unwrap();
+
+    assert_eq!(client.info(), other.info());
+}
+
+#[tokio::test]
+async fn export_range() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    BlockTree::parse(TREE).unwrap().build_client(&client).await;
+
+    let mut fixture = Vec::new();
+
+    // blocks #1 and #2 of the main chain only
+    assert_eq!(
+        2,
+        client
+            .export_blocks(1..3, FixtureFormat::Scale, &mut fixture)
+            .unwrap()
+    );
+
+    let other = Client::new();
+
+    other
+        .import_blocks(&fixture[..], BlockOrigin::File)
+        .await
+        .unwrap();
+
+    assert_eq!(2, other.info().best_number);
+    assert_eq!(0, other.info().finalized_number);
+
+    // importing the same blocks again is a no-op
+    assert_eq!(
+        0,
+        other
+            .import_blocks(&fixture[..], BlockOrigin::File)
+            .await
+            .unwrap()
+    );
+}
+
+#[tokio::test]
+async fn export_forks() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    BlockTree::parse(TREE).unwrap().build_client(&client).await;
+
+    let mut fixture = Vec::new();
+
+    // fork B-X-Y-Z branches off below #3, but reaches into the range
+    assert_eq!(
+        7,
+        client
+            .export_blocks(4.., FixtureFormat::Scale, &mut fixture)
+            .unwrap()
+    );
+
+    // nothing to export above the highest block
+    assert_eq!(
+        0,
+        client
+            .export_blocks(7.., FixtureFormat::Scale, Vec::new())
+            .unwrap()
+    );
+}
+
+#[tokio::test]
+async fn invalid_fixture() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+
+    assert!(client
+        .import_blocks(&b"EMPT\x02\x00\x00\x00\x00"[..], BlockOrigin::File)
+        .await
+        .is_err());
+
+    assert!(client
+        .import_blocks(&br#"{"version": 2, "blocks": []}"#[..], BlockOrigin::File)
+        .await
+        .is_err());
+
+    assert!(client
+        .import_blocks(
+            &br#"{"version": 4294967297, "blocks": []}"#[..],
+            BlockOrigin::File
+        )
+        .await
+        .is_err());
+
+    assert!(client
+        .import_blocks(&b"garbage"[..], BlockOrigin::File)
+        .await
+        .is_err());
+
+    // the version is checked before blocks of an unknown layout are decoded
+    let err = client
+        .import_blocks(&b"EMPT\x02\x00\x00\x00\x04\xff\xff"[..], BlockOrigin::File)
+        .await
+        .unwrap_err();
+
+    assert!(err
+        .to_string()
+        .contains("unsupported chain fixture version: 2"));
+
+    let err = client
+        .import_blocks(
+            &br#"{"version": 2, "blocks": ["ff"]}"#[..],
+            BlockOrigin::File,
+        )
+        .await
+        .unwrap_err();
+
+    assert!(err
+        .to_string()
+        .contains("unsupported chain fixture version: 2"));
+}
@@ -0,0 +1,121 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use sp_core::{
+    sr25519,
+    storage::{ChildInfo, Storage, StorageChild},
+};
+use substrate_test_client::GenesisInit;
+use substrate_test_runtime::{currency::DOLLARS, genesismap::GenesisStorageBuilder, AccountId};
+use substrate_test_runtime_client::AccountKeyring;
+
+#[cfg(test)]
+#[path = "genesis_tests.rs"]
+mod tests;
+
+/// Genesis configuration for a [`crate::Client`]
+///
+/// By default, the genesis configuration of the test runtime is used, i.e. Alice, Bob
+/// and Charlie are authorities and endowed accounts.
+#[derive(Debug, Clone)]
+pub struct Genesis {
+    authorities: Vec<sr25519::Public>,
+    endowed_accounts: Vec<AccountId>,
+    balance: u64,
+    heap_pages: Option<u64>,
+    storage: Storage,
+}
+
+impl Default for Genesis {
+    fn default() -> Self {
+        let accounts = [
+            AccountKeyring::Alice,
+            AccountKeyring::Bob,
+            AccountKeyring::Charlie,
+        ];
+
+        Genesis {
+            authorities: accounts.iter().map(|a| a.public()).collect(),
+            endowed_accounts: accounts.iter().map(|a| a.public()).collect(),
+            balance: 1000 * DOLLARS,
+            heap_pages: None,
+            storage: Default::default(),
+        }
+    }
+}
+
+impl Genesis {
+    /// Set the genesis authority set
+    pub fn authorities(mut self, authorities: Vec<sr25519::Public>) -> Self {
+        self.authorities = authorities;
+        self
+    }
+
+    /// Endow each of `accounts` with `balance`
+    pub fn endowed_accounts(mut self, accounts: Vec<AccountId>, balance: u64) -> Self {
+        self.endowed_accounts = accounts;
+        self.balance = balance;
+        self
+    }
+
+    /// Set the number of extra heap pages available to the runtime
+    pub fn heap_pages(mut self, heap_pages: u64) -> Self {
+        self.heap_pages = Some(heap_pages);
+        self
+    }
+
+    /// Add a raw `key` / `value` pair to the genesis storage.
+    ///
+    /// Raw storage is applied last and overrides any other genesis configuration.
+    pub fn storage(mut self, key: impl AsRef<[u8]>, value: impl AsRef<[u8]>) -> Self {
+        self.storage
+            .top
+            .insert(key.as_ref().to_vec(), value.as_ref().to_vec());
+        self
+    }
+
+    /// Add a raw `key` / `value` pair to the child trie `child_info` of the genesis storage
+    pub fn child_storage(
+        mut self,
+        child_info: &ChildInfo,
+        key: impl AsRef<[u8]>,
+        value: impl AsRef<[u8]>,
+    ) -> Self {
+        self.storage
+            .children_default
+            .entry(child_info.storage_key().to_vec())
+            .or_insert_with(|| StorageChild {
+                data: Default::default(),
+                child_info: child_info.clone(),
+            })
+            .data
+            .insert(key.as_ref().to_vec(), value.as_ref().to_vec());
+        self
+    }
+}
+
+impl GenesisInit for Genesis {
+    fn genesis_storage(&self) -> Storage {
+        GenesisStorageBuilder::new(
+            self.authorities.clone(),
+            self.endowed_accounts.clone(),
+            self.balance,
+        )
+        .with_heap_pages(self.heap_pages)
+        .with_extra_storage(self.storage.clone())
+        .build()
+    }
+}
@@ -0,0 +1,96 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use codec::Encode;
+use sc_client_api::Backend;
+use sp_core::storage::{well_known_keys, ChildInfo};
+use sp_state_machine::Backend as StateBackend;
+use substrate_test_runtime_client::AccountKeyring;
+
+use super::Genesis;
+use crate::{Client, ClientBuilder};
+
+#[test]
+fn raw_storage() {
+    let child_info = ChildInfo::new_default(b"child");
+
+    let genesis = Genesis::default().storage(b"key", b"value").child_storage(
+        &child_info,
+        b"child_key",
+        b"child_value",
+    );
+
+    let client = ClientBuilder::new().genesis(genesis).build();
+    let state = client
+        .as_backend()
+        .state_at(client.info().genesis_hash)
+        .unwrap();
+
+    assert_eq!(Some(b"value".to_vec()), state.storage(b"key").unwrap());
+    assert_eq!(
+        Some(b"child_value".to_vec()),
+        state.child_storage(&child_info, b"child_key").unwrap()
+    );
+}
+
+#[test]
+fn heap_pages() {
+    let client = ClientBuilder::new()
+        .genesis(Genesis::default().heap_pages(16))
+        .build();
+
+    let state = client
+        .as_backend()
+        .state_at(client.info().genesis_hash)
+        .unwrap();
+
+    assert_eq!(
+        Some(16u64.encode()),
+        state.storage(well_known_keys::HEAP_PAGES).unwrap()
+    );
+}
+
+#[test]
+fn authorities() {
+    let genesis = Genesis::default().authorities(vec![
+        AccountKeyring::Dave.public(),
+        AccountKeyring::Eve.public(),
+    ]);
+
+    let client = ClientBuilder::new().genesis(genesis.clone()).build();
+    let other = ClientBuilder::new().genesis(genesis).build();
+
+    // same genesis configuration, same genesis block
+    assert_eq!(client.info().genesis_hash, other.info().genesis_hash);
+
+    // a different validator set results in a different genesis block
+    assert_ne!(
+        client.info().genesis_hash,
+        Client::new().info().genesis_hash
+    );
+}
+
+#[test]
+fn endowed_accounts() {
+    let genesis = Genesis::default().endowed_accounts(vec![AccountKeyring::Ferdie.public()], 42);
+
+    let client = ClientBuilder::new().genesis(genesis).build();
+
+    assert_ne!(
+        client.info().genesis_hash,
+        Client::new().info().genesis_hash
+    );
+}
@@ -16,25 +16,36 @@
 
 #![allow(dead_code)]
 
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
 
-use futures::lock::Mutex as AsyncMutex;
+use futures::{channel::mpsc::UnboundedReceiver, lock::Mutex as AsyncMutex};
+use futures_timer::Delay;
 use parking_lot::Mutex;
 use sc_client_api::backend::TransactionFor;
 use sc_consensus::{
     block_import::JustificationImport, import_queue::Verifier, BlockCheckParams, BlockImport,
-    BlockImportParams, ForkChoiceStrategy, ImportResult,
+    BlockImportParams, ForkChoiceStrategy, ImportResult, StateAction,
 };
 use sc_service::Arc;
 use sp_core::H256;
 use sp_runtime::{
     generic::BlockId,
     traits::{Block, Header, NumberFor},
-    Justification,
+    ConsensusEngineId, Justification, Justifications,
 };
 use substrate_test_runtime_client::{runtime, Backend};
+use tracing::debug;
+
+use crate::{notify::Sinks, Client, ImportStats};
 
-use crate::Client;
+#[cfg(test)]
+#[path = "import_tests.rs"]
+mod tests;
 
 pub trait AnyTransaction:
     BlockImport<
@@ -61,22 +72,61 @@ where
     // empty
 }
 
+/// [`AnyBlockImport`] mode, which clears storage changes, forcing block re-execution
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ClearChanges;
+
+/// [`AnyBlockImport`] mode, which passes storage changes through to the inner block import
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KeepChanges;
+
 /// Implements [`sp_consensus::block_import::BlockImport`] for the any transaction type.
+///
+/// By default, storage changes are cleared and every imported block is re-executed. Use
+/// [`AnyBlockImport::keep_changes`] to pass precomputed storage changes through instead.
 #[derive(Clone)]
-pub struct AnyBlockImport<BI> {
+pub struct AnyBlockImport<BI, M = ClearChanges> {
     inner: BI,
+    stats: Arc<Mutex<ImportStats>>,
+    _mode: PhantomData<M>,
 }
 
 impl<I> AnyBlockImport<I> {
     pub fn new(inner: I) -> Self {
-        Self { inner }
+        Self {
+            inner,
+            stats: Default::default(),
+            _mode: PhantomData,
+        }
+    }
+
+    /// Keep storage changes of imported blocks, skipping block re-execution
+    pub fn keep_changes(self) -> AnyBlockImport<I, KeepChanges> {
+        AnyBlockImport {
+            inner: self.inner,
+            stats: self.stats,
+            _mode: PhantomData,
+        }
+    }
+}
+
+impl<I, M> AnyBlockImport<I, M> {
+    /// Return import timing statistics
+    pub fn stats(&self) -> ImportStats {
+        self.stats.lock().clone()
+    }
+
+    /// Reset import timing statistics
+    pub fn reset_stats(&self) {
+        *self.stats.lock() = ImportStats::default();
     }
 }
 
 #[async_trait::async_trait]
-impl<BI> BlockImport<runtime::Block> for AnyBlockImport<BI>
+impl<B, BI> BlockImport<B> for AnyBlockImport<BI, ClearChanges>
 where
-    BI: BlockImport<runtime::Block, Error = sp_consensus::Error> + Send + Sync,
+    B: Block,
+    BI: BlockImport<B, Error = sp_consensus::Error> + Send + Sync,
     BI::Transaction: Send,
 {
     type Error = sp_consensus::Error;
@@ -85,7 +135,7 @@ where
     /// Check block preconditions
     async fn check_block(
         &mut self,
-        block: BlockCheckParams<runtime::Block>,
+        block: BlockCheckParams<B>,
     ) -> Result<ImportResult, Self::Error> {
         self.inner.check_block(block).await
     }
@@ -93,37 +143,263 @@ where
     /// Import a block
     async fn import_block(
         &mut self,
-        block: BlockImportParams<runtime::Block, Self::Transaction>,
+        block: BlockImportParams<B, Self::Transaction>,
     ) -> Result<ImportResult, Self::Error> {
-        self.inner
+        let start = Instant::now();
+
+        let res = self
+            .inner
             .import_block(block.clear_storage_changes_and_mutate())
-            .await
+            .await;
+
+        self.stats.lock().record(start.elapsed(), false);
+
+        res
+    }
+}
+
+#[async_trait::async_trait]
+impl<B, BI> BlockImport<B> for AnyBlockImport<BI, KeepChanges>
+where
+    B: Block,
+    BI: BlockImport<B, Error = sp_consensus::Error> + Send + Sync,
+    BI::Transaction: Send + 'static,
+{
+    type Error = sp_consensus::Error;
+    type Transaction = BI::Transaction;
+
+    /// Check block preconditions
+    async fn check_block(
+        &mut self,
+        block: BlockCheckParams<B>,
+    ) -> Result<ImportResult, Self::Error> {
+        self.inner.check_block(block).await
+    }
+
+    /// Import a block, applying its storage changes if present
+    async fn import_block(
+        &mut self,
+        block: BlockImportParams<B, Self::Transaction>,
+    ) -> Result<ImportResult, Self::Error> {
+        let with_changes = matches!(block.state_action, StateAction::ApplyChanges(_));
+        let start = Instant::now();
+
+        let res = self.inner.import_block(block).await;
+
+        self.stats.lock().record(start.elapsed(), with_changes);
+
+        res
+    }
+}
+
+/// A fault to be injected by [`FaultyBlockImport`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fault {
+    /// Return [`ImportResult::MissingState`]
+    MissingState,
+    /// Return [`ImportResult::UnknownParent`]
+    UnknownParent,
+    /// Return [`ImportResult::KnownBad`]
+    KnownBad,
+    /// Fail with [`sp_consensus::Error::ClientImport`]
+    Error(String),
+    /// Panic while importing the block
+    Panic,
+    /// Delay the block import, the block will be imported afterwards
+    Delay(Duration),
+}
+
+/// Selects the block imports a [`Fault`] is injected into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultTrigger {
+    /// Block with the given number
+    Number(NumberFor<runtime::Block>),
+    /// Block with the given hash
+    Hash(H256),
+    /// Every nth block import, counting from the first import
+    EveryNth(usize),
+}
+
+impl FaultTrigger {
+    fn matches(&self, header: &runtime::Header, count: usize) -> bool {
+        match self {
+            FaultTrigger::Number(number) => header.number() == number,
+            FaultTrigger::Hash(hash) => header.hash() == *hash,
+            FaultTrigger::EveryNth(n) => *n != 0 && count % n == 0,
+        }
+    }
+}
+
+/// A [`sp_consensus::block_import::BlockImport`] wrapper which can be scripted to fail.
+///
+/// Faults are only injected into [`BlockImport::import_block`]. If more than one fault
+/// matches an import, the fault which has been injected first will be used.
+#[derive(Clone)]
+pub struct FaultyBlockImport<BI> {
+    inner: BI,
+    faults: Arc<Mutex<Vec<(FaultTrigger, Fault)>>>,
+    imports: Arc<AtomicUsize>,
+}
+
+impl<BI> FaultyBlockImport<BI> {
+    pub fn new(inner: BI) -> Self {
+        Self {
+            inner,
+            faults: Default::default(),
+            imports: Default::default(),
+        }
+    }
+
+    /// Inject `fault` into all block imports selected by `trigger`
+    pub fn with_fault(self, trigger: FaultTrigger, fault: Fault) -> Self {
+        self.inject(trigger, fault);
+        self
+    }
+
+    /// Inject `fault` into all block imports selected by `trigger`
+    ///
+    /// Note that faults are shared between all clones of this block import.
+    pub fn inject(&self, trigger: FaultTrigger, fault: Fault) {
+        self.faults.lock().push((trigger, fault));
+    }
+
+    /// Remove all injected faults
+    pub fn clear(&self) {
+        self.faults.lock().clear();
+    }
+
+    /// Return the number of block imports so far, including failed ones
+    pub fn imports(&self) -> usize {
+        self.imports.load(Ordering::SeqCst)
+    }
+
+    fn fault(&self, header: &runtime::Header, count: usize) -> Option<Fault> {
+        self.faults
+            .lock()
+            .iter()
+            .find(|(trigger, _)| trigger.matches(header, count))
+            .map(|(_, fault)| fault.clone())
+    }
+}
+
+#[async_trait::async_trait]
+impl<BI> BlockImport<runtime::Block> for FaultyBlockImport<BI>
+where
+    BI: BlockImport<runtime::Block, Error = sp_consensus::Error> + Send + Sync,
+    BI::Transaction: Send + 'static,
+{
+    type Error = sp_consensus::Error;
+    type Transaction = BI::Transaction;
+
+    /// Check block preconditions
+    async fn check_block(
+        &mut self,
+        block: BlockCheckParams<runtime::Block>,
+    ) -> Result<ImportResult, Self::Error> {
+        self.inner.check_block(block).await
+    }
+
+    /// Import a block, unless a fault has been injected for this block
+    async fn import_block(
+        &mut self,
+        block: BlockImportParams<runtime::Block, Self::Transaction>,
+    ) -> Result<ImportResult, Self::Error> {
+        let count = self.imports.fetch_add(1, Ordering::SeqCst) + 1;
+
+        match self.fault(&block.header, count) {
+            Some(Fault::MissingState) => return Ok(ImportResult::MissingState),
+            Some(Fault::UnknownParent) => return Ok(ImportResult::UnknownParent),
+            Some(Fault::KnownBad) => return Ok(ImportResult::KnownBad),
+            Some(Fault::Error(err)) => return Err(sp_consensus::Error::ClientImport(err)),
+            Some(Fault::Panic) => panic!("injected fault importing block {}", block.header.hash()),
+            Some(Fault::Delay(delay)) => Delay::new(delay).await,
+            None => {}
+        }
+
+        self.inner.import_block(block).await
     }
 }
 
+/// How [`PassThroughVerifier`] treats the justification of a consensus engine
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JustificationPolicy {
+    /// Pass the justification on for import
+    Keep,
+    /// Remove the justification before import
+    Strip,
+    /// Fail verification, if the block has no justification for the engine
+    Require,
+}
+
 /// A Verifier that accepts all justifications and passes them on for import.
 ///
-/// Block finality and fork choice strategy are configurable.
+/// Block finality and fork choice strategy are configurable. The block body,
+/// justifications and any other import parameters are passed on unchanged, unless
+/// a [`JustificationPolicy`] for a consensus engine says otherwise. Justifications of
+/// engines without a policy are kept.
 #[derive(Clone)]
 pub struct PassThroughVerifier {
     finalized: bool,
     fork_choice: ForkChoiceStrategy,
+    policies: HashMap<ConsensusEngineId, JustificationPolicy>,
 }
 
 impl PassThroughVerifier {
     pub fn new(finalized: bool) -> Self {
-        Self {
-            finalized,
-            fork_choice: ForkChoiceStrategy::LongestChain,
-        }
+        Self::new_with_fork_choice(finalized, ForkChoiceStrategy::LongestChain)
     }
 
     pub fn new_with_fork_choice(finalized: bool, fork_choice: ForkChoiceStrategy) -> Self {
         Self {
             finalized,
             fork_choice,
+            policies: HashMap::new(),
         }
     }
+
+    /// Apply `policy` to justifications of consensus engine `engine_id`
+    pub fn with_justification_policy(
+        mut self,
+        engine_id: ConsensusEngineId,
+        policy: JustificationPolicy,
+    ) -> Self {
+        self.policies.insert(engine_id, policy);
+        self
+    }
+
+    fn justifications(
+        &self,
+        justifications: Option<Justifications>,
+    ) -> Result<Option<Justifications>, String> {
+        for (engine_id, policy) in self.policies.iter() {
+            let missing = justifications
+                .as_ref()
+                .map_or(true, |j| j.get(*engine_id).is_none());
+
+            if *policy == JustificationPolicy::Require && missing {
+                return Err(format!(
+                    "Missing justification for engine {}",
+                    String::from_utf8_lossy(engine_id)
+                ));
+            }
+        }
+
+        let justifications = justifications
+            .into_iter()
+            .flatten()
+            .filter(|(engine_id, _)| {
+                self.policies.get(engine_id) != Some(&JustificationPolicy::Strip)
+            })
+            .fold(None, |acc: Option<Justifications>, j| match acc {
+                None => Some(Justifications::from(j)),
+                Some(mut acc) => {
+                    acc.append(j);
+                    Some(acc)
+                }
+            });
+
+        Ok(justifications)
+    }
 }
 
 #[async_trait::async_trait]
@@ -137,30 +413,132 @@ where
     ) -> Result<BlockImportParams<B, ()>, String> {
         block.finalized = self.finalized;
         block.fork_choice = Some(self.fork_choice);
+        block.justifications = self.justifications(block.justifications.take())?;
 
-        Ok(BlockImportParams::new(block.origin, block.header))
+        Ok(block)
     }
 }
 
+/// Verifies justifications imported by [`Finalizer`]
+pub trait JustificationVerifier: Send + Sync {
+    /// Verify `justification` for the block with `hash` and `number`
+    fn verify(
+        &self,
+        hash: H256,
+        number: NumberFor<runtime::Block>,
+        justification: &Justification,
+    ) -> Result<(), String>;
+}
+
+impl<F> JustificationVerifier for F
+where
+    F: Fn(H256, NumberFor<runtime::Block>, &Justification) -> Result<(), String> + Send + Sync,
+{
+    fn verify(
+        &self,
+        hash: H256,
+        number: NumberFor<runtime::Block>,
+        justification: &Justification,
+    ) -> Result<(), String> {
+        self(hash, number, justification)
+    }
+}
+
+/// A [`JustificationVerifier`] accepting any justification
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AcceptAll;
+
+impl JustificationVerifier for AcceptAll {
+    fn verify(
+        &self,
+        _hash: H256,
+        _number: NumberFor<runtime::Block>,
+        _justification: &Justification,
+    ) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+type PendingSource = Box<dyn Fn() -> Vec<(H256, NumberFor<runtime::Block>)> + Send + Sync>;
+
 /// A [`sp_consensus::block_import::JustificationImport`] implementation that
-/// will always finalize the imported block.
-pub struct Finalizer(pub Arc<Client>);
+/// will finalize the imported block, if the justification has been verified.
+///
+/// By default, any justification is accepted and there are no pending justification
+/// requests on start.
+pub struct Finalizer {
+    client: Arc<Client>,
+    verifier: Box<dyn JustificationVerifier>,
+    pending: PendingSource,
+    engines: Option<Vec<ConsensusEngineId>>,
+}
+
+impl Finalizer {
+    pub fn new(client: Arc<Client>) -> Self {
+        Finalizer {
+            client,
+            verifier: Box::new(AcceptAll),
+            pending: Box::new(Vec::new),
+            engines: None,
+        }
+    }
+
+    /// Verify justifications using `verifier` before finalizing a block
+    pub fn with_verifier(mut self, verifier: impl JustificationVerifier + 'static) -> Self {
+        self.verifier = Box::new(verifier);
+        self
+    }
+
+    /// Request the justifications returned by `source` on start
+    pub fn with_pending<F>(mut self, source: F) -> Self
+    where
+        F: Fn() -> Vec<(H256, NumberFor<runtime::Block>)> + Send + Sync + 'static,
+    {
+        self.pending = Box::new(source);
+        self
+    }
+
+    /// Reject justifications of any consensus engine not in `engines`
+    pub fn with_engines(mut self, engines: Vec<ConsensusEngineId>) -> Self {
+        self.engines = Some(engines);
+        self
+    }
+}
 
 #[async_trait::async_trait]
 impl JustificationImport<runtime::Block> for Finalizer {
     type Error = sp_consensus::Error;
 
     async fn on_start(&mut self) -> Vec<(H256, NumberFor<runtime::Block>)> {
-        Vec::new()
+        (self.pending)()
     }
 
     async fn import_justification(
         &mut self,
         hash: H256,
-        _number: NumberFor<runtime::Block>,
+        number: NumberFor<runtime::Block>,
         justification: Justification,
     ) -> Result<(), Self::Error> {
-        self.0
+        if let Some(ref engines) = self.engines {
+            if !engines.contains(&justification.0) {
+                debug!(
+                    target: "emptor",
+                    "Justification for block {} from unknown engine {:?}",
+                    hash,
+                    justification.0
+                );
+                return Err(sp_consensus::Error::InvalidJustification);
+            }
+        }
+
+        self.verifier
+            .verify(hash, number, &justification)
+            .map_err(|err| {
+                debug!(target: "emptor", "Invalid justification for block {}: {}", hash, err);
+                sp_consensus::Error::InvalidJustification
+            })?;
+
+        self.client
             .finalize_block(BlockId::Hash(hash), Some(justification), true)
             .map_err(|_| sp_consensus::Error::InvalidJustification)
     }
@@ -173,6 +551,7 @@ where
 {
     inner: Arc<AsyncMutex<Box<dyn Verifier<B>>>>,
     failed: Arc<Mutex<HashMap<B::Hash, String>>>,
+    sinks: Arc<Sinks<(B::Hash, String)>>,
 }
 
 impl<B> TrackingVerifier<B>
@@ -183,8 +562,34 @@ where
         TrackingVerifier {
             inner: Arc::new(AsyncMutex::new(Box::new(verifier))),
             failed: Default::default(),
+            sinks: Default::default(),
         }
     }
+
+    /// Return the reason verification failed for the block with `hash`
+    pub fn failure(&self, hash: &B::Hash) -> Option<String> {
+        self.failed.lock().get(hash).cloned()
+    }
+
+    /// Return all failed verifications
+    pub fn failures(&self) -> HashMap<B::Hash, String> {
+        self.failed.lock().clone()
+    }
+
+    /// Return the number of failed verifications
+    pub fn failure_count(&self) -> usize {
+        self.failed.lock().len()
+    }
+
+    /// Remove all failed verifications
+    pub fn clear_failures(&self) {
+        self.failed.lock().clear();
+    }
+
+    /// Return a stream of `(hash, reason)` for all verifications failing from now on
+    pub fn failure_stream(&self) -> UnboundedReceiver<(B::Hash, String)> {
+        self.sinks.subscribe()
+    }
 }
 
 #[async_trait::async_trait]
@@ -200,6 +605,7 @@ where
 
         self.inner.lock().await.verify(block).await.map_err(|e| {
             self.failed.lock().insert(hash, e.clone());
+            self.sinks.notify((hash, e.clone()));
             e
         })
     }
@@ -213,6 +619,7 @@ where
         Self {
             inner: self.inner.clone(),
             failed: self.failed.clone(),
+            sinks: self.sinks.clone(),
         }
     }
 }
@@ -0,0 +1,403 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{sync::Arc, time::Duration};
+
+use futures::StreamExt;
+use sc_consensus::{
+    block_import::JustificationImport, import_queue::Verifier, BlockImport, BlockImportParams,
+    ForkChoiceStrategy, ImportResult, StateAction, StorageChanges,
+};
+use sp_consensus::BlockOrigin;
+use sp_core::H256;
+use sp_runtime::{
+    traits::{Block as BlockT, Header as HeaderT},
+    ConsensusEngineId, Justification, Justifications,
+};
+use substrate_test_runtime_client::runtime::Block;
+
+use super::{
+    AnyBlockImport, Fault, FaultTrigger, FaultyBlockImport, Finalizer, JustificationPolicy,
+    PassThroughVerifier, TrackingVerifier,
+};
+use crate::{import_params, BlockTree, Client};
+
+const ENGINE_0: ConsensusEngineId = *b"SMPL";
+const ENGINE_1: ConsensusEngineId = *b"BEEF";
+
+#[tokio::test]
+async fn fault_by_number() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+
+    let mut import = FaultyBlockImport::new(client.clone())
+        .with_fault(FaultTrigger::Number(1), Fault::MissingState);
+
+    let res = import
+        .import_block(client.new_import_params(BlockOrigin::File))
+        .await
+        .unwrap();
+
+    assert_eq!(ImportResult::MissingState, res);
+    assert_eq!(0, client.info().best_number);
+
+    import.clear();
+
+    let res = import
+        .import_block(client.new_import_params(BlockOrigin::File))
+        .await
+        .unwrap();
+
+    assert!(matches!(res, ImportResult::Imported(_)));
+    assert_eq!(1, client.info().best_number);
+    assert_eq!(2, import.imports());
+}
+
+#[tokio::test]
+async fn fault_by_hash() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let mut import = FaultyBlockImport::new(client.clone());
+
+    let params = client.new_import_params(BlockOrigin::File);
+
+    import.inject(
+        FaultTrigger::Hash(params.header.hash()),
+        Fault::Error("bad block".to_string()),
+    );
+
+    assert!(matches!(
+        import.import_block(params).await,
+        Err(sp_consensus::Error::ClientImport(e)) if e == "bad block"
+    ));
+
+    assert_eq!(0, client.info().best_number);
+}
+
+#[tokio::test]
+async fn fault_every_nth() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+
+    let mut import = FaultyBlockImport::new(client.clone())
+        .with_fault(FaultTrigger::EveryNth(2), Fault::KnownBad);
+
+    let mut results = Vec::new();
+
+    for _ in 0..4 {
+        results.push(
+            import
+                .import_block(client.new_import_params(BlockOrigin::File))
+                .await
+                .unwrap(),
+        );
+    }
+
+    assert!(matches!(results[0], ImportResult::Imported(_)));
+    assert_eq!(ImportResult::KnownBad, results[1]);
+    assert!(matches!(results[2], ImportResult::Imported(_)));
+    assert_eq!(ImportResult::KnownBad, results[3]);
+
+    assert_eq!(2, client.info().best_number);
+}
+
+#[tokio::test]
+async fn fault_delay() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+
+    let mut import = FaultyBlockImport::new(client.clone()).with_fault(
+        FaultTrigger::Number(1),
+        Fault::Delay(Duration::from_millis(20)),
+    );
+
+    let now = std::time::Instant::now();
+    let res = import
+        .import_block(client.new_import_params(BlockOrigin::File))
+        .await
+        .unwrap();
+
+    assert!(now.elapsed() >= Duration::from_millis(20));
+    assert!(matches!(res, ImportResult::Imported(_)));
+    assert_eq!(1, client.info().best_number);
+}
+
+#[tokio::test]
+#[should_panic(expected = "injected fault")]
+async fn fault_panic() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+
+    let mut import =
+        FaultyBlockImport::new(client.clone()).with_fault(FaultTrigger::Number(1), Fault::Panic);
+
+    let _ = import
+        .import_block(client.new_import_params(BlockOrigin::File))
+        .await;
+}
+
+// Return verifier input params for a new block with justifications for both engines
+fn verifier_params(client: &Client) -> BlockImportParams<Block, ()> {
+    let mut params = client.new_import_params(BlockOrigin::File);
+
+    params.fork_choice = None;
+    params.justifications = Some({
+        let mut j = Justifications::from((ENGINE_0, vec![1, 2, 3]));
+        j.append((ENGINE_1, vec![4, 5, 6]));
+        j
+    });
+
+    params
+}
+
+#[tokio::test]
+async fn pass_through_keeps_params() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let mut verifier =
+        PassThroughVerifier::new_with_fork_choice(true, ForkChoiceStrategy::Custom(false));
+
+    let params = verifier_params(&client);
+    let body = params.body.clone();
+    let justifications = params.justifications.clone();
+
+    let verified = verifier.verify(params).await.unwrap();
+
+    assert!(verified.finalized);
+    assert_eq!(
+        Some(ForkChoiceStrategy::Custom(false)),
+        verified.fork_choice
+    );
+    assert_eq!(body, verified.body);
+    assert_eq!(justifications, verified.justifications);
+}
+
+#[tokio::test]
+async fn pass_through_strips_justification() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let mut verifier = PassThroughVerifier::new(false)
+        .with_justification_policy(ENGINE_0, JustificationPolicy::Strip)
+        .with_justification_policy(ENGINE_1, JustificationPolicy::Keep);
+
+    let verified = verifier.verify(verifier_params(&client)).await.unwrap();
+
+    assert_eq!(
+        Some(Justifications::from((ENGINE_1, vec![4, 5, 6]))),
+        verified.justifications
+    );
+
+    let mut verifier = PassThroughVerifier::new(false)
+        .with_justification_policy(ENGINE_0, JustificationPolicy::Strip)
+        .with_justification_policy(ENGINE_1, JustificationPolicy::Strip);
+
+    let verified = verifier.verify(verifier_params(&client)).await.unwrap();
+
+    assert_eq!(None, verified.justifications);
+}
+
+#[tokio::test]
+async fn pass_through_requires_justification() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let mut verifier = PassThroughVerifier::new(false)
+        .with_justification_policy(ENGINE_0, JustificationPolicy::Require);
+
+    assert!(verifier.verify(verifier_params(&client)).await.is_ok());
+    assert!(verifier
+        .verify(client.new_import_params(BlockOrigin::File))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn tracking_failures() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let mut verifier = TrackingVerifier::new(
+        PassThroughVerifier::new(false)
+            .with_justification_policy(ENGINE_0, JustificationPolicy::Require),
+    );
+
+    let mut failures = verifier.failure_stream();
+
+    assert!(verifier.verify(verifier_params(&client)).await.is_ok());
+    assert_eq!(0, verifier.failure_count());
+
+    let params = client.new_import_params(BlockOrigin::File);
+    let hash = params.header.hash();
+
+    let err = verifier.verify(params).await.unwrap_err();
+
+    assert_eq!(1, verifier.failure_count());
+    assert_eq!(Some(err.clone()), verifier.failure(&hash));
+    assert_eq!(Some(&err), verifier.failures().get(&hash));
+    assert_eq!(Some((hash, err)), failures.next().await);
+
+    verifier.clone().clear_failures();
+
+    assert_eq!(0, verifier.failure_count());
+    assert_eq!(None, verifier.failure(&hash));
+}
+
+#[tokio::test]
+async fn finalizer_verifies_justification() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let labels = BlockTree::parse("G-A-B-C")
+        .unwrap()
+        .build_client(&client)
+        .await;
+
+    let mut finalizer = Finalizer::new(Arc::new(client.clone())).with_verifier(
+        |_hash: H256, number: u64, justification: &Justification| {
+            if justification.1 == vec![number as u8] {
+                Ok(())
+            } else {
+                Err("bad signature".to_string())
+            }
+        },
+    );
+
+    assert!(matches!(
+        finalizer
+            .import_justification(labels["B"], 2, (ENGINE_0, vec![1]))
+            .await,
+        Err(sp_consensus::Error::InvalidJustification)
+    ));
+
+    assert_eq!(0, client.info().finalized_number);
+
+    finalizer
+        .import_justification(labels["B"], 2, (ENGINE_0, vec![2]))
+        .await
+        .unwrap();
+
+    assert_eq!(labels["B"], client.info().finalized_hash);
+}
+
+#[tokio::test]
+async fn finalizer_rejects_unknown_engine() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let labels = BlockTree::parse("G-A-B")
+        .unwrap()
+        .build_client(&client)
+        .await;
+
+    let mut finalizer = Finalizer::new(Arc::new(client.clone())).with_engines(vec![ENGINE_0]);
+
+    assert!(matches!(
+        finalizer
+            .import_justification(labels["A"], 1, (ENGINE_1, vec![1]))
+            .await,
+        Err(sp_consensus::Error::InvalidJustification)
+    ));
+
+    finalizer
+        .import_justification(labels["A"], 1, (ENGINE_0, vec![1]))
+        .await
+        .unwrap();
+
+    assert_eq!(1, client.info().finalized_number);
+}
+
+#[tokio::test]
+async fn finalizer_pending_requests() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let labels = BlockTree::parse("G-A-B")
+        .unwrap()
+        .build_client(&client)
+        .await;
+
+    let mut finalizer = Finalizer::new(Arc::new(client.clone()));
+
+    assert!(finalizer.on_start().await.is_empty());
+
+    let pending = vec![(labels["A"], 1), (labels["B"], 2)];
+    let source = pending.clone();
+
+    let mut finalizer =
+        Finalizer::new(Arc::new(client.clone())).with_pending(move || source.clone());
+
+    assert_eq!(pending, finalizer.on_start().await);
+}
+
+#[tokio::test]
+async fn keep_storage_changes() {
+    let client = Client::new();
+    let mut import = AnyBlockImport::new(client.as_inner()).keep_changes();
+
+    let built = client
+        .new_block_at(client.info().genesis_hash, Default::default())
+        .unwrap()
+        .build()
+        .unwrap();
+
+    let hash = built.block.hash();
+
+    let mut params = import_params(BlockOrigin::File, built.block);
+    params.state_action = StateAction::ApplyChanges(StorageChanges::Changes(built.storage_changes));
+
+    let res = import.import_block(params).await.unwrap();
+
+    assert!(matches!(res, ImportResult::Imported(_)));
+    assert_eq!(client.info().best_hash, hash);
+
+    let stats = import.stats();
+
+    assert_eq!(stats.imports, 1);
+    assert_eq!(stats.with_changes, 1);
+
+    import.reset_stats();
+
+    assert_eq!(import.stats().imports, 0);
+}
+
+#[tokio::test]
+async fn clear_storage_changes() {
+    let client = Client::new();
+    let mut import = AnyBlockImport::new(client.clone());
+
+    import
+        .import_block(client.new_import_params(BlockOrigin::File))
+        .await
+        .unwrap();
+    import
+        .import_block(client.new_import_params(BlockOrigin::File))
+        .await
+        .unwrap();
+
+    let stats = import.stats();
+
+    assert_eq!(stats.imports, 2);
+    assert_eq!(stats.with_changes, 0);
+    assert!(stats.min <= stats.max);
+    assert_eq!(stats.mean(), Some(stats.total / 2));
+}
@@ -0,0 +1,215 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::{HashSet, VecDeque};
+
+use sc_client_api::Backend;
+use sp_blockchain::{Backend as _, HeaderBackend};
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT, NumberFor, One, Zero};
+
+#[cfg(test)]
+#[path = "invariants_tests.rs"]
+mod tests;
+
+/// A violated chain invariant
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvariantViolation<H, N> {
+    /// Leaf set entries which are not leaves, or leaves descending from the finalized
+    /// block missing from the leaf set
+    Leaves { missing: Vec<H>, unexpected: Vec<H> },
+    /// No canonical block at `number`
+    CanonicalGap { number: N },
+    /// Canonical block at `number` has a different number in its header
+    CanonicalNumber { number: N, hash: H },
+    /// Canonical block at `number` is not a child of the canonical block at `number - 1`
+    CanonicalParent {
+        number: N,
+        hash: H,
+        parent: H,
+        expected: H,
+    },
+    /// Best block is not the canonical block at the best block number
+    BestNotCanonical { best: H, canonical: Option<H> },
+    /// Finalized block is not the canonical block at the finalized block number
+    FinalizedNotCanonical { finalized: H, canonical: Option<H> },
+    /// Best block does not descend from the finalized block
+    BestNotDescendant { best: H, finalized: H },
+    /// Leaf which does not descend from the finalized block has not been pruned
+    DisplacedLeaf { leaf: H, number: N },
+}
+
+/// Check the chain invariants of `backend`, return all violations found.
+///
+/// All blocks reachable from genesis via the children index are visited.
+pub fn check<B>(
+    backend: &sc_client_db::Backend<B>,
+) -> Vec<InvariantViolation<B::Hash, NumberFor<B>>>
+where
+    B: BlockT,
+{
+    let chain = backend.blockchain();
+    let info = chain.info();
+
+    let mut violations = Vec::new();
+
+    // canonical number -> hash mapping
+    let mut number = Zero::zero();
+    let mut parent: Option<B::Hash> = None;
+
+    while number <= info.best_number {
+        let hash = match chain.hash(number).expect("canonical hash query failed") {
+            Some(hash) => hash,
+            None => {
+                violations.push(InvariantViolation::CanonicalGap { number });
+                parent = None;
+                number += One::one();
+                continue;
+            }
+        };
+
+        match chain.header(hash).expect("header query failed") {
+            Some(header) if *header.number() == number => {
+                if let Some(expected) = parent {
+                    if *header.parent_hash() != expected {
+                        violations.push(InvariantViolation::CanonicalParent {
+                            number,
+                            hash,
+                            parent: *header.parent_hash(),
+                            expected,
+                        });
+                    }
+                }
+            }
+            _ => violations.push(InvariantViolation::CanonicalNumber { number, hash }),
+        }
+
+        parent = Some(hash);
+        number += One::one();
+    }
+
+    let canonical = chain
+        .hash(info.best_number)
+        .expect("canonical hash query failed");
+
+    if canonical != Some(info.best_hash) {
+        violations.push(InvariantViolation::BestNotCanonical {
+            best: info.best_hash,
+            canonical,
+        });
+    }
+
+    let canonical = chain
+        .hash(info.finalized_number)
+        .expect("canonical hash query failed");
+
+    if canonical != Some(info.finalized_hash) {
+        violations.push(InvariantViolation::FinalizedNotCanonical {
+            finalized: info.finalized_hash,
+            canonical,
+        });
+    }
+
+    let descends = |hash: B::Hash| {
+        is_descendant::<B>(backend, hash, info.finalized_hash, info.finalized_number)
+    };
+
+    if !descends(info.best_hash) {
+        violations.push(InvariantViolation::BestNotDescendant {
+            best: info.best_hash,
+            finalized: info.finalized_hash,
+        });
+    }
+
+    // leaves
+    let mut leaves = HashSet::new();
+    let mut displaced = HashSet::new();
+    let mut queue = VecDeque::from([info.genesis_hash]);
+
+    while let Some(hash) = queue.pop_front() {
+        let children = chain.children(hash).expect("children query failed");
+
+        if children.is_empty() {
+            if descends(hash) {
+                leaves.insert(hash);
+            } else {
+                displaced.insert(hash);
+            }
+        }
+
+        queue.extend(children);
+    }
+
+    let set = chain.leaves().expect("leaves query failed");
+
+    let missing: Vec<_> = leaves
+        .iter()
+        .filter(|h| !set.contains(h))
+        .copied()
+        .collect();
+
+    let unexpected: Vec<_> = set
+        .iter()
+        .filter(|h| !leaves.contains(*h) && !displaced.contains(*h))
+        .copied()
+        .collect();
+
+    if !missing.is_empty() || !unexpected.is_empty() {
+        violations.push(InvariantViolation::Leaves {
+            missing,
+            unexpected,
+        });
+    }
+
+    for leaf in set.iter().filter(|h| displaced.contains(*h)) {
+        let number = chain
+            .number(*leaf)
+            .expect("number query failed")
+            .expect("leaf is a known block");
+
+        violations.push(InvariantViolation::DisplacedLeaf {
+            leaf: *leaf,
+            number,
+        });
+    }
+
+    violations
+}
+
+// Return whether block `hash` is `ancestor` or one of its descendants
+fn is_descendant<B>(
+    backend: &sc_client_db::Backend<B>,
+    hash: B::Hash,
+    ancestor: B::Hash,
+    number: NumberFor<B>,
+) -> bool
+where
+    B: BlockT,
+{
+    let mut hash = hash;
+
+    loop {
+        let header = match backend.blockchain().header(hash).ok().flatten() {
+            Some(header) => header,
+            None => return false,
+        };
+
+        if *header.number() <= number {
+            return hash == ancestor;
+        }
+
+        hash = *header.parent_hash();
+    }
+}
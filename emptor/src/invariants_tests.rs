@@ -0,0 +1,96 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use super::{check, InvariantViolation};
+use crate::{backend::Block, BlockTree, Client};
+
+#[test]
+fn valid_backend() {
+    sp_tracing::try_init_simple();
+
+    let backend = sc_client_db::Backend::<Block>::new_test(10, 10);
+
+    BlockTree::parse("G-A!-B!-C; B-D-E; C-F")
+        .unwrap()
+        .build_backend(&backend);
+
+    assert_eq!(check(&backend), vec![]);
+}
+
+#[test]
+fn displaced_leaf() {
+    sp_tracing::try_init_simple();
+
+    let backend = sc_client_db::Backend::<Block>::new_test(10, 10);
+
+    let labels = BlockTree::parse("G-Q; G-A!-X-Y-Z; A-B!-C")
+        .unwrap()
+        .build_backend(&backend);
+
+    let violations = check(&backend);
+
+    // `Q` got pruned, since it is below the finalized block, but `Z` did not
+    assert!(violations.contains(&InvariantViolation::DisplacedLeaf {
+        leaf: labels["Z"],
+        number: 4,
+    }));
+
+    assert!(!violations.contains(&InvariantViolation::DisplacedLeaf {
+        leaf: labels["Q"],
+        number: 1,
+    }));
+
+    assert!(violations
+        .iter()
+        .all(|v| matches!(v, InvariantViolation::DisplacedLeaf { .. })));
+}
+
+#[tokio::test]
+async fn random_trees() {
+    sp_tracing::try_init_simple();
+
+    for seed in 0..10 {
+        let client = Client::new();
+        let tree = BlockTree::random(seed, 20);
+        let labels = tree.build_client(&client).await;
+
+        let (displaced, violations): (Vec<_>, Vec<_>) = check(&client.as_backend())
+            .into_iter()
+            .partition(|v| matches!(v, InvariantViolation::DisplacedLeaf { .. }));
+
+        assert_eq!(vec![], violations, "seed {}", seed);
+
+        // displaced leaves are kept, unless they are below the finalized block
+        let mut displaced: Vec<_> = displaced
+            .iter()
+            .filter_map(|v| match v {
+                InvariantViolation::DisplacedLeaf { leaf, .. } => Some(*leaf),
+                _ => None,
+            })
+            .collect();
+
+        let mut expected: Vec<_> = tree
+            .displaced_leaves()
+            .into_iter()
+            .map(|label| labels[label])
+            .collect();
+
+        displaced.sort();
+        expected.sort();
+
+        assert_eq!(expected, displaced, "seed {}", seed);
+    }
+}
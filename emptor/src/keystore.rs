@@ -0,0 +1,83 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+
+use sc_keystore::LocalKeystore;
+use sp_core::crypto::KeyTypeId;
+use sp_keystore::{Keystore, KeystorePtr};
+use vegan_primitives::{Keyring, KEY_TYPE};
+
+#[cfg(test)]
+#[path = "keystore_tests.rs"]
+mod tests;
+
+/// Signature scheme of a keystore key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scheme {
+    Ecdsa,
+    Sr25519,
+    Ed25519,
+}
+
+/// Builder for an in-memory keystore pre-loaded with test keys
+#[derive(Debug, Clone, Default)]
+pub struct KeystoreBuilder {
+    keys: Vec<(KeyTypeId, Scheme, String)>,
+}
+
+impl KeystoreBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the ecdsa keys of `accounts` under [`vegan_primitives::KEY_TYPE`]
+    pub fn keyring(self, accounts: &[Keyring]) -> Self {
+        accounts.iter().fold(self, |builder, account| {
+            builder.key(KEY_TYPE, Scheme::Ecdsa, &account.to_seed())
+        })
+    }
+
+    /// Add the `scheme` key derived from `seed` under `key_type`, e.g. `//Alice`
+    pub fn key(mut self, key_type: KeyTypeId, scheme: Scheme, seed: &str) -> Self {
+        self.keys.push((key_type, scheme, seed.to_string()));
+        self
+    }
+
+    /// Return an in-memory keystore containing all added keys
+    pub fn build(self) -> KeystorePtr {
+        let keystore = LocalKeystore::in_memory();
+
+        for (key_type, scheme, seed) in self.keys.iter() {
+            let seed = Some(seed.as_str());
+
+            match scheme {
+                Scheme::Ecdsa => keystore.ecdsa_generate_new(*key_type, seed).map(|_| ()),
+                Scheme::Sr25519 => keystore.sr25519_generate_new(*key_type, seed).map(|_| ()),
+                Scheme::Ed25519 => keystore.ed25519_generate_new(*key_type, seed).map(|_| ()),
+            }
+            .expect("failed to add key to keystore");
+        }
+
+        Arc::new(keystore)
+    }
+}
+
+/// Return an in-memory keystore containing the ecdsa keys of `accounts` under
+/// [`vegan_primitives::KEY_TYPE`]
+pub fn keystore(accounts: &[Keyring]) -> KeystorePtr {
+    KeystoreBuilder::new().keyring(accounts).build()
+}
@@ -0,0 +1,79 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use sp_core::{crypto::KeyTypeId, ed25519, keccak_256, Pair};
+use substrate_test_runtime_client::AccountKeyring;
+use vegan_primitives::{Keyring, KEY_TYPE};
+
+use super::{keystore, KeystoreBuilder, Scheme};
+
+const OTHER: KeyTypeId = KeyTypeId(*b"othr");
+
+#[test]
+fn keyring_keys() {
+    let keystore = keystore(&[Keyring::Alice, Keyring::Bob]);
+
+    let keys = keystore.ecdsa_public_keys(KEY_TYPE);
+
+    assert_eq!(keys.len(), 2);
+    assert!(keys.contains(&Keyring::Alice.public().into()));
+    assert!(keys.contains(&Keyring::Bob.public().into()));
+    assert!(!keys.contains(&Keyring::Charlie.public().into()));
+}
+
+#[test]
+fn keyring_sign() {
+    let keystore = keystore(&[Keyring::Alice]);
+
+    let msg = b"emptor";
+    let public = Keyring::Alice.public();
+
+    let sig = keystore
+        .ecdsa_sign_prehashed(KEY_TYPE, &public.clone().into(), &keccak_256(msg))
+        .unwrap()
+        .unwrap();
+
+    assert!(Keyring::verify(&public, &sig.into(), msg));
+}
+
+#[test]
+fn any_key_type_and_scheme() {
+    let keystore = KeystoreBuilder::new()
+        .keyring(&[Keyring::Alice])
+        .key(OTHER, Scheme::Sr25519, &AccountKeyring::Bob.to_seed())
+        .key(OTHER, Scheme::Ed25519, "//Charlie")
+        .key(OTHER, Scheme::Ecdsa, "//Dave")
+        .build();
+
+    assert_eq!(keystore.ecdsa_public_keys(KEY_TYPE).len(), 1);
+
+    assert_eq!(
+        keystore.sr25519_public_keys(OTHER),
+        vec![AccountKeyring::Bob.public()]
+    );
+
+    assert_eq!(
+        keystore.ed25519_public_keys(OTHER),
+        vec![ed25519::Pair::from_string("//Charlie", None)
+            .unwrap()
+            .public()]
+    );
+
+    assert_eq!(
+        keystore.ecdsa_public_keys(OTHER),
+        vec![Keyring::Dave.public().into()]
+    );
+}
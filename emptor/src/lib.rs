@@ -1,12 +1,55 @@
-mod backend;
+mod auxiliary;
+pub mod backend;
+mod builder;
 mod client;
+mod clock;
+mod finality;
+mod fixture;
+mod genesis;
 mod import;
+pub mod invariants;
+mod keystore;
+mod notify;
+mod pool;
+pub mod queue;
+mod recording;
+mod reorg;
+mod runtime;
+mod seal;
+mod select;
+mod stats;
+mod tree;
+mod wait;
 
-pub use client::Client;
-pub use import::{AnyBlockImport, Finalizer, PassThroughVerifier, TrackingVerifier};
+pub use auxiliary::{AuxError, AuxStorage};
+pub use builder::{ClientBuilder, Database};
+pub use client::{import_params, Backend, Client, InnerClient};
+pub use clock::{MockClock, DEFAULT_SLOT_DURATION};
+pub use finality::{FinalityController, FinalityError, FinalityRecord, Violation};
+pub use fixture::FixtureFormat;
+pub use genesis::Genesis;
+pub use import::{
+    AcceptAll, AnyBlockImport, ClearChanges, Fault, FaultTrigger, FaultyBlockImport, Finalizer,
+    JustificationPolicy, JustificationVerifier, KeepChanges, PassThroughVerifier, TrackingVerifier,
+};
+pub use keystore::{keystore, KeystoreBuilder, Scheme};
+pub use pool::{TransactionPool, TransactionStatus};
+pub use queue::{ImportQueueHarness, LinkEvent};
+pub use recording::{Call, CheckRecord, ImportRecord, RecordingBlockImport};
+pub use reorg::{ReorgEvent, ReorgObserver};
+pub use runtime::{MockAnswers, MockRuntimeApi, MockRuntimeClient};
+pub use seal::{seal_block, ConsensusBlockBuilder, SealVerifier};
+pub use select::{
+    weight_digest, CappedLongestChain, FixedBest, ForkChoice, ForkChoiceVerifier, GhostChain,
+    HeaviestChain,
+};
+pub use stats::ImportStats;
+pub use tree::{BlockTree, Labels, DEFAULT_ENGINE_ID};
+pub use wait::WaitError;
 
 /// Import various trait extensions and structs which are used by the [`Client`]
 pub mod prelude {
+    pub use sc_client_db::{BlocksPruning, PruningMode};
     pub use substrate_test_runtime_client::{
         runtime::{Block, Hash},
         Backend, BlockBuilderExt, ClientBlockImportExt, ClientExt, DefaultTestClientBuilderExt,
@@ -0,0 +1,52 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use parking_lot::Mutex;
+
+/// A set of subscribers, each receiving a copy of every notification.
+///
+/// Subscribers which dropped their receiving end are removed on the next notification.
+pub(crate) struct Sinks<T> {
+    sinks: Mutex<Vec<UnboundedSender<T>>>,
+}
+
+impl<T> Default for Sinks<T> {
+    fn default() -> Self {
+        Sinks {
+            sinks: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl<T> Sinks<T>
+where
+    T: Clone,
+{
+    /// Return a new subscriber stream
+    pub(crate) fn subscribe(&self) -> UnboundedReceiver<T> {
+        let (tx, rx) = unbounded();
+        self.sinks.lock().push(tx);
+        rx
+    }
+
+    /// Send `item` to all subscribers
+    pub(crate) fn notify(&self, item: T) {
+        self.sinks
+            .lock()
+            .retain(|sink| sink.unbounded_send(item.clone()).is_ok());
+    }
+}
@@ -0,0 +1,288 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use codec::{Decode, Encode};
+use parking_lot::Mutex;
+use sc_client_api::{BlockBackend, StorageProvider};
+use sp_blockchain::HeaderBackend;
+use sp_consensus::BlockOrigin;
+use sp_core::{
+    hashing::{blake2_128, twox_128},
+    storage::StorageKey,
+    H256,
+};
+use sp_runtime::traits::{BlakeTwo256, Block as BlockT, Hash as HashT, Header as HeaderT};
+use substrate_test_runtime::{AccountId, Balance, Extrinsic, Transfer};
+use substrate_test_runtime_client::AccountKeyring;
+use tracing::debug;
+
+use crate::Client;
+
+#[cfg(test)]
+#[path = "pool_tests.rs"]
+mod tests;
+
+/// Status of a pool transaction with respect to the current best chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionStatus {
+    /// Transaction is waiting for inclusion
+    Ready,
+    /// Transaction is included in a block of the best chain
+    InBlock(H256),
+    /// Transaction has been included in blocks which are no longer part of the best chain
+    Retracted,
+    /// Transaction could not be applied and has been dropped from the pool
+    Invalid,
+    /// Transaction is not known to the pool
+    Unknown,
+}
+
+#[derive(Default)]
+struct State {
+    ready: Vec<(H256, Extrinsic)>,
+    included: HashMap<H256, (Extrinsic, Vec<H256>)>,
+    invalid: HashSet<H256>,
+    nonces: HashMap<AccountId, u64>,
+    senders: HashMap<H256, AccountId>,
+}
+
+/// An in-memory transaction pool stand-in for a [`Client`]
+///
+/// Account nonces are tracked by the pool, starting from the account nonce at the best
+/// block. Nonces are not reset for retracted transactions, use
+/// [`TransactionPool::resubmit_retracted`] instead.
+///
+/// Transactions which can not be applied when authoring a block are dropped and the
+/// nonce of their sender is read from chain state again.
+#[derive(Clone)]
+pub struct TransactionPool {
+    client: Client,
+    state: Arc<Mutex<State>>,
+}
+
+impl TransactionPool {
+    pub fn new(client: Client) -> Self {
+        TransactionPool {
+            client,
+            state: Default::default(),
+        }
+    }
+
+    /// Submit a signed transfer of `amount` from `from` to `to` using the next nonce of `from`
+    pub fn submit_transfer(
+        &self,
+        from: AccountKeyring,
+        to: AccountKeyring,
+        amount: Balance,
+    ) -> H256 {
+        let mut state = self.state.lock();
+
+        let nonce = match state.nonces.get(&from.public()) {
+            Some(nonce) => *nonce,
+            None => self.account_nonce(from.public()),
+        };
+
+        state.nonces.insert(from.public(), nonce + 1);
+
+        let transfer = Transfer {
+            from: from.pair(),
+            to: to.public(),
+            amount,
+            nonce,
+        };
+
+        let xt = transfer.into_unchecked_extrinsic();
+        let hash = BlakeTwo256::hash_of(&xt);
+
+        state.senders.insert(hash, from.public());
+        state.ready.push((hash, xt));
+
+        hash
+    }
+
+    /// Submit an arbitrary extrinsic, return the transaction hash
+    pub fn submit(&self, xt: Extrinsic) -> H256 {
+        let hash = BlakeTwo256::hash_of(&xt);
+        self.state.lock().ready.push((hash, xt));
+        hash
+    }
+
+    /// Return all transactions waiting for inclusion, in submission order
+    pub fn ready(&self) -> Vec<Extrinsic> {
+        self.state
+            .lock()
+            .ready
+            .iter()
+            .map(|(_, xt)| xt.clone())
+            .collect()
+    }
+
+    /// Return the status of the transaction with `hash`
+    pub fn status(&self, hash: H256) -> TransactionStatus {
+        let state = self.state.lock();
+
+        if state.ready.iter().any(|(h, _)| *h == hash) {
+            return TransactionStatus::Ready;
+        }
+
+        if state.invalid.contains(&hash) {
+            return TransactionStatus::Invalid;
+        }
+
+        match state.included.get(&hash) {
+            Some((_, blocks)) => blocks
+                .iter()
+                .find(|b| self.is_canonical(**b))
+                .map_or(TransactionStatus::Retracted, |b| {
+                    TransactionStatus::InBlock(*b)
+                }),
+            None => TransactionStatus::Unknown,
+        }
+    }
+
+    /// Return the hashes of all retracted transactions
+    pub fn retracted(&self) -> Vec<H256> {
+        let hashes: Vec<_> = self.state.lock().included.keys().copied().collect();
+
+        hashes
+            .into_iter()
+            .filter(|h| self.status(*h) == TransactionStatus::Retracted)
+            .collect()
+    }
+
+    /// Move all retracted transactions back to the ready queue
+    pub fn resubmit_retracted(&self) -> usize {
+        let retracted = self.retracted();
+        let mut state = self.state.lock();
+
+        for hash in retracted.iter() {
+            if let Some((xt, _)) = state.included.remove(hash) {
+                state.ready.push((*hash, xt));
+            }
+        }
+
+        retracted.len()
+    }
+
+    /// Build a block at `parent`, or at best block if `parent` is `None`, containing all
+    /// ready transactions which can be applied, and import it.
+    ///
+    /// Included transactions are removed from the ready queue, transactions which can
+    /// not be applied are dropped as invalid. Return the block hash.
+    pub async fn author_block(&self, parent: Option<H256>) -> H256 {
+        let parent = parent.unwrap_or_else(|| self.client.info().best_hash);
+
+        let mut builder = self
+            .client
+            .new_block_at(parent, Default::default())
+            .expect("failed to create a new block");
+
+        let mut pushed = Vec::new();
+        let mut invalid = Vec::new();
+
+        for (hash, xt) in self.state.lock().ready.iter() {
+            match builder.push(xt.clone()) {
+                Ok(()) => pushed.push(*hash),
+                Err(err) => {
+                    debug!(target: "emptor", "Drop invalid transaction {}: {}", hash, err);
+                    invalid.push(*hash);
+                }
+            }
+        }
+
+        self.drop_invalid(invalid);
+
+        let built = builder.build().expect("failed to build block");
+        let hash = built.block.hash();
+
+        self.client
+            .import_built_block(BlockOrigin::Own, built)
+            .await
+            .expect("block import failed");
+
+        let mut state = self.state.lock();
+
+        for tx in pushed {
+            if let Some(pos) = state.ready.iter().position(|(h, _)| *h == tx) {
+                let (_, xt) = state.ready.remove(pos);
+                state.included.insert(tx, (xt, Vec::new()));
+            }
+
+            if let Some((_, blocks)) = state.included.get_mut(&tx) {
+                blocks.push(hash);
+            }
+        }
+
+        hash
+    }
+
+    /// Return the transactions included in block `hash`
+    pub fn block_transactions(&self, hash: H256) -> Vec<Extrinsic> {
+        self.client
+            .inner
+            .block_body(hash)
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+    }
+
+    // Remove `invalid` transactions from the ready queue, forget the nonces of their senders
+    fn drop_invalid(&self, invalid: Vec<H256>) {
+        let mut state = self.state.lock();
+
+        state.ready.retain(|(h, _)| !invalid.contains(h));
+
+        for hash in invalid {
+            if let Some(sender) = state.senders.remove(&hash) {
+                state.nonces.remove(&sender);
+            }
+
+            state.invalid.insert(hash);
+        }
+    }
+
+    // Return the nonce of account `who` at the best block
+    fn account_nonce(&self, who: AccountId) -> u64 {
+        let mut key = twox_128(b"System").to_vec();
+        key.extend(twox_128(b"Account"));
+        key.extend(blake2_128(&who.encode()));
+        key.extend(who.encode());
+
+        self.client
+            .inner
+            .storage(self.client.info().best_hash, &StorageKey(key))
+            .ok()
+            .flatten()
+            .and_then(|data| u64::decode(&mut &data.0[..]).ok())
+            .unwrap_or_default()
+    }
+
+    fn is_canonical(&self, hash: H256) -> bool {
+        self.client
+            .inner
+            .header(hash)
+            .ok()
+            .flatten()
+            .map_or(false, |h| {
+                self.client.inner.hash(*h.number()).ok().flatten() == Some(hash)
+            })
+    }
+}
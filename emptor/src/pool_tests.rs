@@ -0,0 +1,143 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use substrate_test_runtime::Transfer;
+use substrate_test_runtime_client::AccountKeyring;
+
+use super::{TransactionPool, TransactionStatus};
+use crate::Client;
+
+#[tokio::test]
+async fn transfers_included() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let pool = TransactionPool::new(client.clone());
+
+    let tx1 = pool.submit_transfer(AccountKeyring::Alice, AccountKeyring::Bob, 10);
+    let tx2 = pool.submit_transfer(AccountKeyring::Alice, AccountKeyring::Charlie, 20);
+
+    assert_eq!(2, pool.ready().len());
+    assert_eq!(TransactionStatus::Ready, pool.status(tx1));
+
+    let hash = pool.author_block(None).await;
+
+    assert_eq!(hash, client.info().best_hash);
+    assert!(pool.ready().is_empty());
+    assert_eq!(2, pool.block_transactions(hash).len());
+    assert_eq!(TransactionStatus::InBlock(hash), pool.status(tx1));
+    assert_eq!(TransactionStatus::InBlock(hash), pool.status(tx2));
+}
+
+#[tokio::test]
+async fn unknown_transaction() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let pool = TransactionPool::new(client.clone());
+
+    let tx = pool.submit_transfer(AccountKeyring::Alice, AccountKeyring::Bob, 10);
+    let other = TransactionPool::new(client);
+
+    assert_eq!(TransactionStatus::Unknown, other.status(tx));
+}
+
+#[tokio::test]
+async fn retracted_after_reorg() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let pool = TransactionPool::new(client.clone());
+    let genesis = client.info().genesis_hash;
+
+    let tx = pool.submit_transfer(AccountKeyring::Alice, AccountKeyring::Bob, 10);
+    let a = pool.author_block(None).await;
+
+    assert_eq!(TransactionStatus::InBlock(a), pool.status(tx));
+
+    // empty fork overtaking the block containing `tx`
+    let b = pool.author_block(Some(genesis)).await;
+    let c = pool.author_block(Some(b)).await;
+
+    assert_eq!(c, client.info().best_hash);
+    assert_eq!(TransactionStatus::Retracted, pool.status(tx));
+    assert_eq!(vec![tx], pool.retracted());
+
+    assert_eq!(1, pool.resubmit_retracted());
+    assert_eq!(TransactionStatus::Ready, pool.status(tx));
+
+    let d = pool.author_block(None).await;
+
+    assert_eq!(TransactionStatus::InBlock(d), pool.status(tx));
+    assert!(pool.retracted().is_empty());
+}
+
+#[tokio::test]
+async fn nonce_from_chain_state() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let pool = TransactionPool::new(client.clone());
+
+    pool.submit_transfer(AccountKeyring::Alice, AccountKeyring::Bob, 10);
+    pool.author_block(None).await;
+
+    // a second pool starts from the nonce at the best block
+    let other = TransactionPool::new(client.clone());
+
+    let tx = other.submit_transfer(AccountKeyring::Alice, AccountKeyring::Bob, 10);
+    let hash = other.author_block(None).await;
+
+    assert_eq!(TransactionStatus::InBlock(hash), other.status(tx));
+}
+
+#[tokio::test]
+async fn invalid_dropped() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let pool = TransactionPool::new(client.clone());
+
+    let stale = pool.submit_transfer(AccountKeyring::Alice, AccountKeyring::Bob, 10);
+
+    // nonce 0 has been used by another pool already
+    let other = TransactionPool::new(client.clone());
+    other.submit_transfer(AccountKeyring::Alice, AccountKeyring::Bob, 10);
+    other.author_block(None).await;
+
+    let future = pool.submit(
+        Transfer {
+            from: AccountKeyring::Bob.pair(),
+            to: AccountKeyring::Alice.public(),
+            amount: 10,
+            nonce: 5,
+        }
+        .into_unchecked_extrinsic(),
+    );
+
+    let hash = pool.author_block(None).await;
+
+    assert!(pool.ready().is_empty());
+    assert!(pool.block_transactions(hash).is_empty());
+    assert_eq!(TransactionStatus::Invalid, pool.status(stale));
+    assert_eq!(TransactionStatus::Invalid, pool.status(future));
+
+    // the nonce of Alice is read from chain state again
+    let tx = pool.submit_transfer(AccountKeyring::Alice, AccountKeyring::Bob, 10);
+    let hash = pool.author_block(None).await;
+
+    assert_eq!(TransactionStatus::InBlock(hash), pool.status(tx));
+}
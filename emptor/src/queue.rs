@@ -0,0 +1,377 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::future::poll_fn;
+use parking_lot::Mutex;
+use sc_client_api::backend::TransactionFor;
+use sc_consensus::{
+    import_queue::{
+        BasicQueue, BlockImportResult, BlockImportStatus, BoxJustificationImport, ImportQueue,
+        IncomingBlock, Link, RuntimeOrigin, Verifier,
+    },
+    BlockImport,
+};
+use sp_consensus::BlockOrigin;
+use sp_core::testing::TaskExecutor;
+use sp_runtime::{
+    traits::{Block as BlockT, Header as HeaderT, NumberFor},
+    Justification, Justifications,
+};
+use substrate_test_runtime::{Block, Hash};
+use substrate_test_runtime_client::Backend;
+
+use crate::Client;
+
+#[cfg(test)]
+#[path = "queue_tests.rs"]
+mod tests;
+
+/// Result of a single block import, the imported block number or the import error
+pub type BlockResult = (Result<NumberFor<Block>, String>, Hash);
+
+/// A callback received by the [`Link`] of an [`ImportQueueHarness`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkEvent {
+    /// A batch of blocks has been processed
+    BlocksProcessed {
+        imported: usize,
+        count: usize,
+        results: Vec<BlockResult>,
+    },
+    /// A justification has been processed
+    JustificationImported {
+        who: RuntimeOrigin,
+        hash: Hash,
+        number: NumberFor<Block>,
+        success: bool,
+    },
+    /// The import queue requested a justification
+    RequestJustification {
+        hash: Hash,
+        number: NumberFor<Block>,
+    },
+}
+
+// Kind of a link event, each kind is consumed by waiters independently
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    Blocks,
+    Justifications,
+    Requests,
+}
+
+impl Kind {
+    fn matches(&self, event: &LinkEvent) -> bool {
+        match self {
+            Kind::Blocks => matches!(event, LinkEvent::BlocksProcessed { .. }),
+            Kind::Justifications => matches!(event, LinkEvent::JustificationImported { .. }),
+            Kind::Requests => matches!(event, LinkEvent::RequestJustification { .. }),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+struct RecordingLink {
+    events: Arc<Mutex<Vec<LinkEvent>>>,
+}
+
+impl Link<Block> for RecordingLink {
+    fn blocks_processed(
+        &mut self,
+        imported: usize,
+        count: usize,
+        results: Vec<(BlockImportResult<Block>, Hash)>,
+    ) {
+        let results = results
+            .into_iter()
+            .map(|(res, hash)| {
+                let res = match res {
+                    Ok(BlockImportStatus::ImportedKnown(number, _))
+                    | Ok(BlockImportStatus::ImportedUnknown(number, _, _)) => Ok(number),
+                    Err(err) => Err(format!("{:?}", err)),
+                };
+
+                (res, hash)
+            })
+            .collect();
+
+        self.events.lock().push(LinkEvent::BlocksProcessed {
+            imported,
+            count,
+            results,
+        });
+    }
+
+    fn justification_imported(
+        &mut self,
+        who: RuntimeOrigin,
+        hash: &Hash,
+        number: NumberFor<Block>,
+        success: bool,
+    ) {
+        self.events.lock().push(LinkEvent::JustificationImported {
+            who,
+            hash: *hash,
+            number,
+            success,
+        });
+    }
+
+    fn request_justification(&mut self, hash: &Hash, number: NumberFor<Block>) {
+        self.events.lock().push(LinkEvent::RequestJustification {
+            hash: *hash,
+            number,
+        });
+    }
+}
+
+/// Drives an [`sc_consensus::BasicQueue`] without any networking.
+///
+/// Blocks and justifications are pushed as if they had been received from the network.
+/// Results are collected by a recording [`Link`], whenever the harness waits for them.
+pub struct ImportQueueHarness {
+    queue: BasicQueue<Block, TransactionFor<Backend, Block>>,
+    link: RecordingLink,
+    // number of link events consumed by waiters, per event kind
+    seen: [usize; 3],
+}
+
+impl ImportQueueHarness {
+    /// Return a harness importing blocks into `client`, verified by `verifier`
+    pub fn new<V>(client: &Client, verifier: V) -> Self
+    where
+        V: Verifier<Block> + 'static,
+    {
+        Self::with_block_import(verifier, client.clone(), None)
+    }
+
+    /// Return a harness using `verifier`, `block_import` and `justification_import`
+    pub fn with_block_import<V, BI>(
+        verifier: V,
+        block_import: BI,
+        justification_import: Option<BoxJustificationImport<Block>>,
+    ) -> Self
+    where
+        V: Verifier<Block> + 'static,
+        BI: BlockImport<
+                Block,
+                Error = sp_consensus::Error,
+                Transaction = TransactionFor<Backend, Block>,
+            > + Send
+            + Sync
+            + 'static,
+    {
+        let queue = BasicQueue::new(
+            verifier,
+            Box::new(block_import),
+            justification_import,
+            &TaskExecutor::new(),
+            None,
+        );
+
+        ImportQueueHarness {
+            queue,
+            link: RecordingLink::default(),
+            seen: Default::default(),
+        }
+    }
+
+    /// Push `blocks` to the import queue
+    pub fn import_blocks(&mut self, origin: BlockOrigin, blocks: Vec<Block>) {
+        let blocks = blocks.into_iter().map(|b| incoming(b, None)).collect();
+        self.import_incoming(origin, blocks);
+    }
+
+    /// Push `blocks` with full control over the incoming block fields
+    pub fn import_incoming(&mut self, origin: BlockOrigin, blocks: Vec<IncomingBlock<Block>>) {
+        self.queue.service_ref().import_blocks(origin, blocks);
+    }
+
+    /// Push a `justification` for block `hash` received from `who`
+    pub fn import_justification(
+        &mut self,
+        who: RuntimeOrigin,
+        hash: Hash,
+        number: NumberFor<Block>,
+        justification: Justification,
+    ) {
+        self.queue.service_ref().import_justifications(
+            who,
+            hash,
+            number,
+            Justifications::from(justification),
+        );
+    }
+
+    /// Wait until at least `count` block import results have been reported since the
+    /// last wait for them, return those results.
+    pub async fn wait_for_blocks(
+        &mut self,
+        count: usize,
+        timeout: Duration,
+    ) -> Result<Vec<BlockResult>, String> {
+        let events = self
+            .wait(Kind::Blocks, timeout, |events| {
+                let results: usize = events
+                    .iter()
+                    .map(|e| match e {
+                        LinkEvent::BlocksProcessed { results, .. } => results.len(),
+                        _ => 0,
+                    })
+                    .sum();
+
+                results >= count
+            })
+            .await
+            .map_err(|n| format!("timeout waiting for {} block results, got {}", count, n))?;
+
+        Ok(events
+            .into_iter()
+            .flat_map(|e| match e {
+                LinkEvent::BlocksProcessed { results, .. } => results,
+                _ => Vec::new(),
+            })
+            .collect())
+    }
+
+    /// Wait until at least `count` justification imports have been reported since the
+    /// last wait for them, return `(hash, success)` for each of them.
+    pub async fn wait_for_justifications(
+        &mut self,
+        count: usize,
+        timeout: Duration,
+    ) -> Result<Vec<(Hash, bool)>, String> {
+        let events = self
+            .wait(Kind::Justifications, timeout, |events| {
+                events.len() >= count
+            })
+            .await
+            .map_err(|n| format!("timeout waiting for {} justifications, got {}", count, n))?;
+
+        Ok(events
+            .into_iter()
+            .filter_map(|e| match e {
+                LinkEvent::JustificationImported { hash, success, .. } => Some((hash, success)),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// Wait until at least `count` justification requests have been reported since the
+    /// last wait for them, return the requested blocks.
+    pub async fn wait_for_requests(
+        &mut self,
+        count: usize,
+        timeout: Duration,
+    ) -> Result<Vec<(Hash, NumberFor<Block>)>, String> {
+        let events = self
+            .wait(Kind::Requests, timeout, |events| events.len() >= count)
+            .await
+            .map_err(|n| format!("timeout waiting for {} requests, got {}", count, n))?;
+
+        Ok(events
+            .into_iter()
+            .filter_map(|e| match e {
+                LinkEvent::RequestJustification { hash, number } => Some((hash, number)),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// Return all link events received so far
+    pub fn events(&self) -> Vec<LinkEvent> {
+        self.link.events.lock().clone()
+    }
+
+    // Poll the queue until `done` holds for the unseen link events of `kind`, mark them
+    // as seen. Events of other kinds are left for their waiters. On timeout, return the
+    // number of unseen link events of `kind`.
+    async fn wait<F>(
+        &mut self,
+        kind: Kind,
+        timeout: Duration,
+        done: F,
+    ) -> Result<Vec<LinkEvent>, usize>
+    where
+        F: Fn(&[LinkEvent]) -> bool,
+    {
+        let seen = self.seen[kind as usize];
+        let queue = &mut self.queue;
+        let link = &mut self.link;
+
+        let events = |link: &RecordingLink| -> Vec<LinkEvent> {
+            link.events
+                .lock()
+                .iter()
+                .filter(|e| kind.matches(e))
+                .skip(seen)
+                .cloned()
+                .collect()
+        };
+
+        let poll = poll_fn(|cx: &mut Context| {
+            queue.poll_actions(cx, link);
+
+            let unseen = events(link);
+
+            if done(&unseen) {
+                Poll::Ready(unseen)
+            } else {
+                Poll::Pending
+            }
+        });
+
+        let res = tokio::time::timeout(timeout, poll).await;
+
+        match res {
+            Ok(unseen) => {
+                self.seen[kind as usize] += unseen.len();
+                Ok(unseen)
+            }
+            Err(_) => Err(events(&self.link).len()),
+        }
+    }
+}
+
+/// Return an [`IncomingBlock`] for `block`, received from `origin`
+pub fn incoming(block: Block, origin: Option<RuntimeOrigin>) -> IncomingBlock<Block> {
+    let (header, body) = block.deconstruct();
+
+    IncomingBlock {
+        hash: header.hash(),
+        header: Some(header),
+        body: Some(body),
+        indexed_body: None,
+        justifications: None,
+        origin,
+        allow_missing_state: false,
+        skip_execution: false,
+        import_existing: false,
+        state: None,
+    }
+}
+
+/// Return a peer id for peer `n`, to be used as block or justification origin
+pub fn peer(n: u8) -> RuntimeOrigin {
+    // identity multihash of a single byte
+    RuntimeOrigin::from_bytes(&[0, 1, n]).expect("valid peer id")
+}
@@ -0,0 +1,208 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{sync::Arc, time::Duration};
+
+use sp_blockchain::HeaderBackend;
+use sp_consensus::BlockOrigin;
+use sp_runtime::{traits::Block as BlockT, ConsensusEngineId};
+use substrate_test_runtime::Block;
+
+use super::{incoming, peer, ImportQueueHarness};
+use crate::{Client, Finalizer, JustificationPolicy, PassThroughVerifier, TrackingVerifier};
+
+const ENGINE_ID: ConsensusEngineId = *b"QUEU";
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+// Build and import `n` blocks on top of the best block of `client`
+async fn blocks(client: &Client, n: usize) -> Vec<Block> {
+    let mut blocks = Vec::new();
+
+    for _ in 0..n {
+        let built = client
+            .new_block_at(client.info().best_hash, Default::default())
+            .unwrap()
+            .build()
+            .unwrap();
+
+        blocks.push(built.block.clone());
+
+        client
+            .import_built_block(BlockOrigin::Own, built)
+            .await
+            .unwrap();
+    }
+
+    blocks
+}
+
+#[tokio::test]
+async fn import_blocks() {
+    sp_tracing::try_init_simple();
+
+    let source = Client::new();
+    let client = Client::new();
+
+    let blocks = blocks(&source, 3).await;
+    let hashes: Vec<_> = blocks.iter().map(|b| b.hash()).collect();
+
+    let mut harness = ImportQueueHarness::new(&client, PassThroughVerifier::new(false));
+
+    harness.import_blocks(BlockOrigin::NetworkBroadcast, blocks);
+
+    let results = harness.wait_for_blocks(3, TIMEOUT).await.unwrap();
+
+    assert_eq!(
+        vec![(Ok(1), hashes[0]), (Ok(2), hashes[1]), (Ok(3), hashes[2])],
+        results
+    );
+    assert_eq!(hashes[2], client.info().best_hash);
+}
+
+#[tokio::test]
+async fn unknown_parent() {
+    sp_tracing::try_init_simple();
+
+    let source = Client::new();
+    let client = Client::new();
+
+    let blocks = blocks(&source, 2).await;
+    let hash = blocks[1].hash();
+
+    let mut harness = ImportQueueHarness::new(&client, PassThroughVerifier::new(false));
+
+    harness.import_incoming(
+        BlockOrigin::NetworkBroadcast,
+        vec![incoming(blocks[1].clone(), Some(peer(1)))],
+    );
+
+    let results = harness.wait_for_blocks(1, TIMEOUT).await.unwrap();
+
+    assert_eq!(1, results.len());
+    assert_eq!(hash, results[0].1);
+    assert!(results[0].0.is_err());
+    assert_eq!(0, client.info().best_number);
+}
+
+#[tokio::test]
+async fn failed_verification() {
+    sp_tracing::try_init_simple();
+
+    let source = Client::new();
+    let client = Client::new();
+
+    let blocks = blocks(&source, 1).await;
+    let hash = blocks[0].hash();
+
+    let verifier = TrackingVerifier::new(
+        PassThroughVerifier::new(false)
+            .with_justification_policy(ENGINE_ID, JustificationPolicy::Require),
+    );
+
+    let mut harness = ImportQueueHarness::new(&client, verifier.clone());
+
+    harness.import_blocks(BlockOrigin::NetworkBroadcast, blocks);
+
+    let results = harness.wait_for_blocks(1, TIMEOUT).await.unwrap();
+
+    assert!(results[0].0.is_err());
+    assert!(verifier.failure(&hash).is_some());
+}
+
+#[tokio::test]
+async fn import_justification() {
+    sp_tracing::try_init_simple();
+
+    let source = Client::new();
+    let client = Client::new();
+
+    let blocks = blocks(&source, 2).await;
+    let hash = blocks[1].hash();
+
+    let finalizer = Finalizer::new(Arc::new(client.clone())).with_engines(vec![ENGINE_ID]);
+
+    let mut harness = ImportQueueHarness::with_block_import(
+        PassThroughVerifier::new(false),
+        client.clone(),
+        Some(Box::new(finalizer)),
+    );
+
+    harness.import_blocks(BlockOrigin::NetworkBroadcast, blocks);
+    harness.wait_for_blocks(2, TIMEOUT).await.unwrap();
+
+    harness.import_justification(peer(1), hash, 2, (*b"OTHR", vec![1]));
+    harness.import_justification(peer(2), hash, 2, (ENGINE_ID, vec![2]));
+
+    let results = harness.wait_for_justifications(2, TIMEOUT).await.unwrap();
+
+    assert_eq!(vec![(hash, false), (hash, true)], results);
+    assert_eq!(hash, client.info().finalized_hash);
+}
+
+#[tokio::test]
+async fn independent_waiters() {
+    sp_tracing::try_init_simple();
+
+    let source = Client::new();
+    let client = Client::new();
+
+    let blocks = blocks(&source, 3).await;
+    let hashes: Vec<_> = blocks.iter().map(|b| b.hash()).collect();
+
+    let finalizer = Finalizer::new(Arc::new(client.clone())).with_engines(vec![ENGINE_ID]);
+
+    let mut harness = ImportQueueHarness::with_block_import(
+        PassThroughVerifier::new(false),
+        client.clone(),
+        Some(Box::new(finalizer)),
+    );
+
+    harness.import_blocks(BlockOrigin::NetworkBroadcast, blocks[..2].to_vec());
+    harness.wait_for_blocks(2, TIMEOUT).await.unwrap();
+
+    harness.import_blocks(BlockOrigin::NetworkBroadcast, blocks[2..].to_vec());
+    harness.import_justification(peer(1), hashes[1], 2, (ENGINE_ID, vec![1]));
+
+    let results = harness.wait_for_justifications(1, TIMEOUT).await.unwrap();
+
+    assert_eq!(vec![(hashes[1], true)], results);
+
+    // block results reported while waiting for justifications are not consumed
+    let results = harness.wait_for_blocks(1, TIMEOUT).await.unwrap();
+
+    assert_eq!(vec![(Ok(3), hashes[2])], results);
+}
+
+#[tokio::test]
+async fn request_justifications() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let genesis = client.info().genesis_hash;
+
+    let finalizer =
+        Finalizer::new(Arc::new(client.clone())).with_pending(move || vec![(genesis, 0)]);
+
+    let mut harness = ImportQueueHarness::with_block_import(
+        PassThroughVerifier::new(false),
+        client.clone(),
+        Some(Box::new(finalizer)),
+    );
+
+    let requests = harness.wait_for_requests(1, TIMEOUT).await.unwrap();
+
+    assert_eq!(vec![(genesis, 0)], requests);
+}
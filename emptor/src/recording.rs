@@ -0,0 +1,219 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{borrow::Cow, sync::Arc};
+
+use futures::channel::mpsc::UnboundedReceiver;
+use parking_lot::Mutex;
+use sc_consensus::{
+    BlockCheckParams, BlockImport, BlockImportParams, ForkChoiceStrategy, ImportResult,
+};
+use sp_consensus::BlockOrigin;
+use sp_core::H256;
+use sp_runtime::{traits::Header as HeaderT, DigestItem, Justifications};
+use substrate_test_runtime_client::runtime::{Block, Extrinsic, Header};
+
+use crate::notify::Sinks;
+
+#[cfg(test)]
+#[path = "recording_tests.rs"]
+mod tests;
+
+/// A recorded [`BlockImport::check_block`] call
+#[derive(Debug)]
+pub struct CheckRecord {
+    /// Parameters passed to `check_block`
+    pub params: BlockCheckParams<Block>,
+    /// Result of the call, errors are converted to their string representation
+    pub result: Result<ImportResult, String>,
+}
+
+/// A recorded [`BlockImport::import_block`] call
+#[derive(Debug)]
+pub struct ImportRecord {
+    pub origin: BlockOrigin,
+    pub header: Header,
+    pub body: Option<Vec<Extrinsic>>,
+    pub justifications: Option<Justifications>,
+    pub finalized: bool,
+    pub fork_choice: Option<ForkChoiceStrategy>,
+    pub post_digests: Vec<DigestItem>,
+    /// Keys of the intermediate values, the values themselves are opaque
+    pub intermediates: Vec<Cow<'static, [u8]>>,
+    /// Result of the call, errors are converted to their string representation
+    pub result: Result<ImportResult, String>,
+}
+
+impl ImportRecord {
+    /// Return the hash of the imported block
+    pub fn hash(&self) -> H256 {
+        self.header.hash()
+    }
+
+    /// Return whether the block has been imported successfully
+    pub fn is_imported(&self) -> bool {
+        matches!(self.result, Ok(ImportResult::Imported(_)))
+    }
+}
+
+/// A single recorded call
+#[derive(Debug, Clone)]
+pub enum Call {
+    CheckBlock(Arc<CheckRecord>),
+    ImportBlock(Arc<ImportRecord>),
+}
+
+#[derive(Default)]
+struct Records {
+    checks: Mutex<Vec<Arc<CheckRecord>>>,
+    imports: Mutex<Vec<Arc<ImportRecord>>>,
+    sinks: Sinks<Call>,
+}
+
+/// A [`sp_consensus::block_import::BlockImport`] wrapper which records all calls.
+///
+/// Records are shared between all clones of this block import.
+#[derive(Clone)]
+pub struct RecordingBlockImport<BI> {
+    inner: BI,
+    records: Arc<Records>,
+}
+
+impl<BI> RecordingBlockImport<BI> {
+    pub fn new(inner: BI) -> Self {
+        Self {
+            inner,
+            records: Default::default(),
+        }
+    }
+
+    /// Return all recorded `check_block` calls
+    pub fn checks(&self) -> Vec<Arc<CheckRecord>> {
+        self.records.checks.lock().clone()
+    }
+
+    /// Return all recorded `import_block` calls
+    pub fn imports(&self) -> Vec<Arc<ImportRecord>> {
+        self.records.imports.lock().clone()
+    }
+
+    /// Return all recorded `import_block` calls for the block with `hash`
+    pub fn imports_of(&self, hash: H256) -> Vec<Arc<ImportRecord>> {
+        self.records
+            .imports
+            .lock()
+            .iter()
+            .filter(|r| r.hash() == hash)
+            .cloned()
+            .collect()
+    }
+
+    /// Return the hashes of all successfully imported blocks, in import order
+    pub fn imported(&self) -> Vec<H256> {
+        self.records
+            .imports
+            .lock()
+            .iter()
+            .filter(|r| r.is_imported())
+            .map(|r| r.hash())
+            .collect()
+    }
+
+    /// Remove all records
+    pub fn clear(&self) {
+        self.records.checks.lock().clear();
+        self.records.imports.lock().clear();
+    }
+
+    /// Return a stream of all calls recorded from now on
+    pub fn stream(&self) -> UnboundedReceiver<Call> {
+        self.records.sinks.subscribe()
+    }
+}
+
+#[async_trait::async_trait]
+impl<BI> BlockImport<Block> for RecordingBlockImport<BI>
+where
+    BI: BlockImport<Block, Error = sp_consensus::Error> + Send + Sync,
+    BI::Transaction: Send + 'static,
+{
+    type Error = sp_consensus::Error;
+    type Transaction = BI::Transaction;
+
+    /// Check block preconditions and record the call
+    async fn check_block(
+        &mut self,
+        block: BlockCheckParams<Block>,
+    ) -> Result<ImportResult, Self::Error> {
+        let params = block.clone();
+        let result = self.inner.check_block(block).await;
+
+        let record = Arc::new(CheckRecord {
+            params,
+            result: result.as_ref().map(clone_result).map_err(|e| e.to_string()),
+        });
+
+        self.records.checks.lock().push(record.clone());
+        self.records.sinks.notify(Call::CheckBlock(record));
+
+        result
+    }
+
+    /// Import a block and record the call
+    async fn import_block(
+        &mut self,
+        block: BlockImportParams<Block, Self::Transaction>,
+    ) -> Result<ImportResult, Self::Error> {
+        let origin = block.origin;
+        let header = block.header.clone();
+        let body = block.body.clone();
+        let justifications = block.justifications.clone();
+        let finalized = block.finalized;
+        let fork_choice = block.fork_choice;
+        let post_digests = block.post_digests.clone();
+        let intermediates = block.intermediates.keys().cloned().collect();
+
+        let result = self.inner.import_block(block).await;
+
+        let record = Arc::new(ImportRecord {
+            origin,
+            header,
+            body,
+            justifications,
+            finalized,
+            fork_choice,
+            post_digests,
+            intermediates,
+            result: result.as_ref().map(clone_result).map_err(|e| e.to_string()),
+        });
+
+        self.records.imports.lock().push(record.clone());
+        self.records.sinks.notify(Call::ImportBlock(record));
+
+        result
+    }
+}
+
+// `ImportResult` does not implement `Clone`
+fn clone_result(result: &ImportResult) -> ImportResult {
+    match result {
+        ImportResult::Imported(aux) => ImportResult::Imported(aux.clone()),
+        ImportResult::AlreadyInChain => ImportResult::AlreadyInChain,
+        ImportResult::KnownBad => ImportResult::KnownBad,
+        ImportResult::UnknownParent => ImportResult::UnknownParent,
+        ImportResult::MissingState => ImportResult::MissingState,
+    }
+}
@@ -0,0 +1,117 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use futures::StreamExt;
+use sc_consensus::{BlockCheckParams, BlockImport, ForkChoiceStrategy, ImportResult};
+use sp_consensus::BlockOrigin;
+use sp_runtime::{ConsensusEngineId, Justifications};
+
+use super::{Call, RecordingBlockImport};
+use crate::Client;
+
+const ENGINE_ID: ConsensusEngineId = *b"SMPL";
+
+#[tokio::test]
+async fn record_import() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let mut import = RecordingBlockImport::new(client.clone());
+
+    let mut params = client.new_import_params(BlockOrigin::NetworkBroadcast);
+    let hash = params.header.hash();
+
+    params.finalized = true;
+    params.justifications = Some(Justifications::from((ENGINE_ID, vec![1, 2, 3])));
+
+    import.import_block(params).await.unwrap();
+
+    let records = import.imports_of(hash);
+
+    assert_eq!(1, records.len());
+
+    let record = &records[0];
+
+    assert_eq!(BlockOrigin::NetworkBroadcast, record.origin);
+    assert!(record.finalized);
+    assert!(record.is_imported());
+    assert_eq!(Some(ForkChoiceStrategy::LongestChain), record.fork_choice);
+    assert_eq!(
+        Some(Justifications::from((ENGINE_ID, vec![1, 2, 3]))),
+        record.justifications
+    );
+
+    assert_eq!(vec![hash], import.imported());
+    assert_eq!(1, client.info().finalized_number);
+}
+
+#[tokio::test]
+async fn record_check() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let mut import = RecordingBlockImport::new(client.clone());
+
+    let genesis = client.info().genesis_hash;
+
+    let params = BlockCheckParams {
+        hash: genesis,
+        number: 0,
+        parent_hash: Default::default(),
+        allow_missing_state: false,
+        allow_missing_parent: false,
+        import_existing: false,
+    };
+
+    let res = import.check_block(params.clone()).await.unwrap();
+
+    assert_eq!(ImportResult::AlreadyInChain, res);
+
+    let checks = import.checks();
+
+    assert_eq!(1, checks.len());
+    assert_eq!(params, checks[0].params);
+    assert_eq!(Ok(ImportResult::AlreadyInChain), checks[0].result);
+
+    import.clear();
+
+    assert!(import.checks().is_empty());
+}
+
+#[tokio::test]
+async fn record_stream() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let mut import = RecordingBlockImport::new(client.clone());
+    let mut stream = import.stream();
+
+    for _ in 0..3 {
+        import
+            .import_block(client.new_import_params(BlockOrigin::NetworkBroadcast))
+            .await
+            .unwrap();
+    }
+
+    for n in 1..=3u64 {
+        match stream.next().await {
+            Some(Call::ImportBlock(record)) => assert_eq!(n, record.header.number),
+            call => panic!("unexpected call: {:?}", call),
+        }
+    }
+
+    assert_eq!(3, import.imported().len());
+}
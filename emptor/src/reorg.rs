@@ -0,0 +1,181 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+
+use futures::{channel::mpsc::UnboundedReceiver, lock::Mutex as AsyncMutex, FutureExt, StreamExt};
+use parking_lot::Mutex;
+use sc_client_api::{BlockImportNotification, BlockchainEvents, ImportNotifications};
+use sp_blockchain::tree_route;
+use sp_runtime::traits::{Header as HeaderT, NumberFor};
+use substrate_test_runtime::{Block, Hash};
+use tracing::debug;
+
+use crate::{notify::Sinks, Client};
+
+#[cfg(test)]
+#[path = "reorg_tests.rs"]
+mod tests;
+
+/// A switch of the best chain to a block which does not extend the previous best block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReorgEvent {
+    pub old_best: Hash,
+    pub new_best: Hash,
+    /// Common ancestor of the old and the new best block
+    pub common_ancestor: Hash,
+    pub common_number: NumberFor<Block>,
+    /// Enacted blocks, ending with the new best block
+    pub enacted: Vec<Hash>,
+    /// Retracted blocks, starting with the old best block
+    pub retracted: Vec<Hash>,
+    /// Number of retracted blocks
+    pub depth: usize,
+}
+
+struct Inner {
+    stream: AsyncMutex<ImportNotifications<Block>>,
+    best: Mutex<Hash>,
+    events: Mutex<Vec<ReorgEvent>>,
+    sinks: Sinks<ReorgEvent>,
+}
+
+/// Follows the import notifications of a [`Client`] and reports best chain reorgs.
+///
+/// Pending notifications are processed whenever events are queried. Alternatively,
+/// [`ReorgObserver::run`] can be spawned to process notifications as they arrive.
+#[derive(Clone)]
+pub struct ReorgObserver {
+    client: Client,
+    inner: Arc<Inner>,
+}
+
+impl ReorgObserver {
+    pub fn new(client: Client) -> Self {
+        let inner = Inner {
+            stream: AsyncMutex::new(client.inner.import_notification_stream()),
+            best: Mutex::new(client.info().best_hash),
+            events: Default::default(),
+            sinks: Default::default(),
+        };
+
+        ReorgObserver {
+            client,
+            inner: Arc::new(inner),
+        }
+    }
+
+    /// Process import notifications until the client notification stream is closed
+    pub async fn run(self) {
+        loop {
+            let notification = self.inner.stream.lock().await.next().await;
+
+            match notification {
+                Some(notification) => self.handle(notification),
+                None => return,
+            }
+        }
+    }
+
+    /// Process all pending import notifications
+    pub fn process(&self) {
+        if let Some(mut stream) = self.inner.stream.try_lock() {
+            while let Some(Some(notification)) = stream.next().now_or_never() {
+                self.handle(notification);
+            }
+        }
+    }
+
+    /// Return all reorgs observed so far
+    pub fn events(&self) -> Vec<ReorgEvent> {
+        self.process();
+        self.inner.events.lock().clone()
+    }
+
+    /// Return a stream of reorgs observed from now on
+    pub fn stream(&self) -> UnboundedReceiver<ReorgEvent> {
+        self.inner.sinks.subscribe()
+    }
+
+    /// Return the depth of the deepest reorg observed so far
+    pub fn max_depth(&self) -> usize {
+        self.events()
+            .iter()
+            .map(|e| e.depth)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Panic, if a reorg deeper than `depth` has been observed
+    pub fn assert_max_depth(&self, depth: usize) {
+        if let Some(event) = self.events().iter().find(|e| e.depth > depth) {
+            panic!(
+                "reorg depth {} exceeds maximum depth {}: {:?}",
+                event.depth, depth, event
+            );
+        }
+    }
+
+    fn handle(&self, notification: BlockImportNotification<Block>) {
+        if !notification.is_new_best {
+            return;
+        }
+
+        let old_best = std::mem::replace(&mut *self.inner.best.lock(), notification.hash);
+        let parent = *notification.header.parent_hash();
+        let finalized = self.client.info().finalized_number;
+
+        // The client provides the route from the previous best block to the parent of the
+        // new best block, unless the new best block extends the previous best block. The
+        // best block can change without an import, so the cached best block is a fallback.
+        let route = match notification.tree_route {
+            Some(route) => route,
+            None if parent == old_best => return,
+            None => match tree_route(&*self.client.inner, old_best, parent) {
+                // finalizing a fork moves the best block without an import notification,
+                // which leaves the cached best block on a retracted fork
+                Ok(route) if route.common_block().number < finalized => return,
+                Ok(route) => Arc::new(route),
+                Err(err) => {
+                    debug!(target: "emptor", "No tree route to {}: {}", parent, err);
+                    return;
+                }
+            },
+        };
+
+        let Some(old_best) = route.retracted().first().map(|b| b.hash) else {
+            return;
+        };
+
+        let mut enacted: Vec<_> = route.enacted().iter().map(|b| b.hash).collect();
+        enacted.push(notification.hash);
+
+        let event = ReorgEvent {
+            old_best,
+            new_best: notification.hash,
+            common_ancestor: route.common_block().hash,
+            common_number: route.common_block().number,
+            enacted,
+            retracted: route.retracted().iter().map(|b| b.hash).collect(),
+            depth: route.retracted().len(),
+        };
+
+        debug!(target: "emptor", "Reorg: {:?}", event);
+
+        self.inner.events.lock().push(event.clone());
+        self.inner.sinks.notify(event);
+    }
+}
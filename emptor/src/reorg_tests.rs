@@ -0,0 +1,143 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use futures::StreamExt;
+use sp_consensus::BlockOrigin;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+
+use super::{ReorgEvent, ReorgObserver};
+use crate::{BlockTree, Client};
+
+#[tokio::test]
+async fn no_reorg() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let observer = ReorgObserver::new(client.clone());
+
+    BlockTree::parse("G-A-B-C; A-X")
+        .unwrap()
+        .build_client(&client)
+        .await;
+
+    assert_eq!(Vec::<ReorgEvent>::new(), observer.events());
+    assert_eq!(0, observer.max_depth());
+}
+
+#[tokio::test]
+async fn enacted_and_retracted() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let observer = ReorgObserver::new(client.clone());
+    let mut stream = observer.stream();
+
+    let labels = BlockTree::parse("G-A-B-C; A-X-Y-Z")
+        .unwrap()
+        .build_client(&client)
+        .await;
+
+    let expected = ReorgEvent {
+        old_best: labels["C"],
+        new_best: labels["Z"],
+        common_ancestor: labels["A"],
+        common_number: 1,
+        enacted: vec![labels["X"], labels["Y"], labels["Z"]],
+        retracted: vec![labels["C"], labels["B"]],
+        depth: 2,
+    };
+
+    assert_eq!(vec![expected.clone()], observer.events());
+    assert_eq!(Some(expected), stream.next().await);
+    assert_eq!(2, observer.max_depth());
+
+    observer.assert_max_depth(2);
+}
+
+#[tokio::test]
+#[should_panic(expected = "reorg depth 2 exceeds maximum depth 1")]
+async fn max_depth_exceeded() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let observer = ReorgObserver::new(client.clone());
+
+    BlockTree::parse("G-A-B-C; A-X-Y-Z")
+        .unwrap()
+        .build_client(&client)
+        .await;
+
+    observer.assert_max_depth(1);
+}
+
+#[tokio::test]
+async fn run_observer() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let observer = ReorgObserver::new(client.clone());
+    let mut stream = observer.stream();
+
+    tokio::spawn(observer.clone().run());
+
+    let labels = BlockTree::parse("G-A-B; G-X-Y-Z")
+        .unwrap()
+        .build_client(&client)
+        .await;
+
+    let event = stream.next().await.unwrap();
+
+    assert_eq!(labels["G"], event.common_ancestor);
+    assert_eq!(vec![labels["B"], labels["A"]], event.retracted);
+    assert_eq!(vec![labels["X"], labels["Y"], labels["Z"]], event.enacted);
+}
+
+#[tokio::test]
+async fn finalized_fork() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let observer = ReorgObserver::new(client.clone());
+
+    let labels = BlockTree::parse("G-A-B-C; A-X")
+        .unwrap()
+        .build_client(&client)
+        .await;
+
+    // finalizing `X` makes it the best block, without an import notification
+    client
+        .finalize_block(BlockId::Hash(labels["X"]), None, true)
+        .unwrap();
+
+    assert_eq!(labels["X"], client.info().best_hash);
+
+    let built = client
+        .new_block_at(labels["X"], Default::default())
+        .unwrap()
+        .build()
+        .unwrap();
+
+    let hash = built.block.hash();
+
+    client
+        .import_built_block(BlockOrigin::Own, built)
+        .await
+        .unwrap();
+
+    // `Y` only extends the best chain
+    assert_eq!(hash, client.info().best_hash);
+    assert_eq!(Vec::<ReorgEvent>::new(), observer.events());
+}
@@ -0,0 +1,371 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::HashMap,
+    ops::{Deref, DerefMut},
+    sync::Arc,
+};
+
+use parking_lot::Mutex;
+use sc_client_api::{
+    backend::Finalizer, BlockchainEvents, ClientImportOperation, FinalityNotifications,
+    ImportNotifications, StorageEventStream, StorageKey,
+};
+use sp_api::{
+    ApiError, ApiExt, ApiRef, Core, Metadata, ProofRecorder, ProvideRuntimeApi, RuntimeApiInfo,
+    RuntimeVersion, StorageChanges, StorageProof, TransactionOutcome,
+};
+use sp_blockchain::{HeaderBackend, Info};
+use sp_core::traits::CallContext;
+use sp_runtime::{
+    traits::{HashFor, NumberFor},
+    Justification,
+};
+use sp_state_machine::InMemoryBackend;
+use substrate_test_runtime::{Block, Hash, Header, RuntimeApi};
+use substrate_test_runtime_client::{Backend, LocalExecutorDispatch};
+
+use crate::{Client, InnerClient};
+
+#[cfg(test)]
+#[path = "runtime_tests.rs"]
+mod tests;
+
+type TestInnerClient = InnerClient<Block, LocalExecutorDispatch, RuntimeApi>;
+
+/// Per-block answers of a mocked runtime API call.
+///
+/// Blocks without a registered answer get the default answer.
+#[derive(Debug, Clone)]
+pub struct MockAnswers<T> {
+    default: Arc<Mutex<T>>,
+    answers: Arc<Mutex<HashMap<Hash, T>>>,
+}
+
+impl<T> MockAnswers<T>
+where
+    T: Clone,
+{
+    pub fn new(default: T) -> Self {
+        MockAnswers {
+            default: Arc::new(Mutex::new(default)),
+            answers: Default::default(),
+        }
+    }
+
+    /// Answer `value` for block `hash`
+    pub fn set(&self, hash: Hash, value: T) {
+        self.answers.lock().insert(hash, value);
+    }
+
+    /// Answer `value` for all blocks without a registered answer
+    pub fn set_default(&self, value: T) {
+        *self.default.lock() = value;
+    }
+
+    /// Return the answer for block `hash`
+    pub fn get(&self, hash: Hash) -> T {
+        self.answers
+            .lock()
+            .get(&hash)
+            .cloned()
+            .unwrap_or_else(|| self.default.lock().clone())
+    }
+}
+
+impl<T> Default for MockAnswers<T>
+where
+    T: Clone + Default,
+{
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+/// Runtime API of a [`MockRuntimeClient`].
+///
+/// Dereferences to the mock API `M`, so calls of the mocked runtime APIs are answered by
+/// the mock. Only [`Core`] and [`Metadata`] calls are forwarded to the test runtime, since
+/// forwarding any other API would shadow a mock of the same API. Use the runtime API of
+/// [`MockRuntimeClient::client`] to call other test runtime APIs.
+///
+/// [`ApiExt`] version queries are answered for the APIs registered with
+/// [`MockRuntimeClient::with_api`] and forwarded to the test runtime otherwise. Proof
+/// recording is not supported.
+pub struct MockRuntimeApi<M> {
+    mock: M,
+    apis: HashMap<[u8; 8], u32>,
+    client: Arc<TestInnerClient>,
+}
+
+impl<M> MockRuntimeApi<M> {
+    // Return the version of API `A`, if it is mocked
+    fn mocked<A>(&self) -> Option<u32>
+    where
+        A: RuntimeApiInfo + ?Sized,
+    {
+        self.apis.get(&A::ID).copied()
+    }
+}
+
+impl<M> Deref for MockRuntimeApi<M> {
+    type Target = M;
+
+    fn deref(&self) -> &M {
+        &self.mock
+    }
+}
+
+impl<M> DerefMut for MockRuntimeApi<M> {
+    fn deref_mut(&mut self) -> &mut M {
+        &mut self.mock
+    }
+}
+
+impl<M> Core<Block> for MockRuntimeApi<M> {
+    fn __runtime_api_internal_call_api_at(
+        &self,
+        at: Hash,
+        params: Vec<u8>,
+        fn_name: &dyn Fn(RuntimeVersion) -> &'static str,
+    ) -> Result<Vec<u8>, ApiError> {
+        Core::__runtime_api_internal_call_api_at(&*self.client.runtime_api(), at, params, fn_name)
+    }
+}
+
+impl<M> Metadata<Block> for MockRuntimeApi<M> {
+    fn __runtime_api_internal_call_api_at(
+        &self,
+        at: Hash,
+        params: Vec<u8>,
+        fn_name: &dyn Fn(RuntimeVersion) -> &'static str,
+    ) -> Result<Vec<u8>, ApiError> {
+        Metadata::__runtime_api_internal_call_api_at(
+            &*self.client.runtime_api(),
+            at,
+            params,
+            fn_name,
+        )
+    }
+}
+
+impl<M> ApiExt<Block> for MockRuntimeApi<M> {
+    type StateBackend = InMemoryBackend<HashFor<Block>>;
+
+    fn execute_in_transaction<F, R>(&self, call: F) -> R
+    where
+        F: FnOnce(&Self) -> TransactionOutcome<R>,
+        Self: Sized,
+    {
+        call(self).into_inner()
+    }
+
+    fn has_api<A>(&self, at: Hash) -> Result<bool, ApiError>
+    where
+        A: RuntimeApiInfo + ?Sized,
+        Self: Sized,
+    {
+        match self.mocked::<A>() {
+            Some(_) => Ok(true),
+            None => self.client.runtime_api().has_api::<A>(at),
+        }
+    }
+
+    fn has_api_with<A, P>(&self, at: Hash, pred: P) -> Result<bool, ApiError>
+    where
+        A: RuntimeApiInfo + ?Sized,
+        P: Fn(u32) -> bool,
+        Self: Sized,
+    {
+        match self.mocked::<A>() {
+            Some(version) => Ok(pred(version)),
+            None => self.client.runtime_api().has_api_with::<A, _>(at, pred),
+        }
+    }
+
+    fn api_version<A>(&self, at: Hash) -> Result<Option<u32>, ApiError>
+    where
+        A: RuntimeApiInfo + ?Sized,
+        Self: Sized,
+    {
+        match self.mocked::<A>() {
+            Some(version) => Ok(Some(version)),
+            None => self.client.runtime_api().api_version::<A>(at),
+        }
+    }
+
+    fn record_proof(&mut self) {
+        // proof recording is not supported, no proof will be recorded
+    }
+
+    fn extract_proof(&mut self) -> Option<StorageProof> {
+        None
+    }
+
+    fn proof_recorder(&self) -> Option<ProofRecorder<Block>> {
+        None
+    }
+
+    fn into_storage_changes(
+        &self,
+        _: &Self::StateBackend,
+        _: Hash,
+    ) -> Result<StorageChanges<Self::StateBackend, Block>, String>
+    where
+        Self: Sized,
+    {
+        Err("storage changes are not supported by mock runtime APIs".to_string())
+    }
+
+    fn set_call_context(&mut self, _: CallContext) {
+        // calls are forwarded with the default context
+    }
+}
+
+/// A [`Client`] wrapper, whose [`ProvideRuntimeApi::runtime_api`] returns a
+/// [`MockRuntimeApi`] for the mock API `M`.
+///
+/// The mock API is usually implemented using [`sp_api::mock_impl_runtime_apis`] for the
+/// engine APIs the test runtime does not provide. Register those APIs with
+/// [`MockRuntimeClient::with_api`], so [`ApiExt::has_api`] and [`ApiExt::api_version`]
+/// report them. All other client traits are implemented by the wrapped [`Client`].
+#[derive(Clone)]
+pub struct MockRuntimeClient<M> {
+    client: Client,
+    api: M,
+    apis: HashMap<[u8; 8], u32>,
+}
+
+impl<M> MockRuntimeClient<M>
+where
+    M: Clone,
+{
+    pub fn new(client: Client, api: M) -> Self {
+        MockRuntimeClient {
+            client,
+            api,
+            apis: HashMap::new(),
+        }
+    }
+
+    /// Register API `A` as implemented by the mock API
+    pub fn with_api<A>(mut self) -> Self
+    where
+        A: RuntimeApiInfo + ?Sized,
+    {
+        self.apis.insert(A::ID, A::VERSION);
+        self
+    }
+
+    /// Return the wrapped client
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Return the mock API
+    pub fn api(&self) -> &M {
+        &self.api
+    }
+}
+
+impl<M> ProvideRuntimeApi<Block> for MockRuntimeClient<M>
+where
+    M: Clone + Send + Sync,
+{
+    type Api = MockRuntimeApi<M>;
+
+    fn runtime_api(&self) -> ApiRef<'_, Self::Api> {
+        MockRuntimeApi {
+            mock: self.api.clone(),
+            apis: self.apis.clone(),
+            client: self.client.inner.clone(),
+        }
+        .into()
+    }
+}
+
+impl<M> HeaderBackend<Block> for MockRuntimeClient<M>
+where
+    M: Send + Sync,
+{
+    fn header(&self, hash: Hash) -> sp_blockchain::Result<Option<Header>> {
+        self.client.inner.header(hash)
+    }
+
+    fn info(&self) -> Info<Block> {
+        self.client.inner.info()
+    }
+
+    fn status(&self, hash: Hash) -> sp_blockchain::Result<sp_blockchain::BlockStatus> {
+        self.client.inner.status(hash)
+    }
+
+    fn number(&self, hash: Hash) -> sp_blockchain::Result<Option<NumberFor<Block>>> {
+        self.client.inner.number(hash)
+    }
+
+    fn hash(&self, number: NumberFor<Block>) -> sp_blockchain::Result<Option<Hash>> {
+        self.client.inner.hash(number)
+    }
+}
+
+impl<M> BlockchainEvents<Block> for MockRuntimeClient<M> {
+    fn import_notification_stream(&self) -> ImportNotifications<Block> {
+        self.client.inner.import_notification_stream()
+    }
+
+    fn every_import_notification_stream(&self) -> ImportNotifications<Block> {
+        self.client.inner.every_import_notification_stream()
+    }
+
+    fn finality_notification_stream(&self) -> FinalityNotifications<Block> {
+        self.client.inner.finality_notification_stream()
+    }
+
+    fn storage_changes_notification_stream(
+        &self,
+        filter_keys: Option<&[StorageKey]>,
+        child_filter_keys: Option<&[(StorageKey, Option<Vec<StorageKey>>)]>,
+    ) -> sp_blockchain::Result<StorageEventStream<Hash>> {
+        self.client
+            .inner
+            .storage_changes_notification_stream(filter_keys, child_filter_keys)
+    }
+}
+
+impl<M> Finalizer<Block, Backend> for MockRuntimeClient<M> {
+    fn apply_finality(
+        &self,
+        operation: &mut ClientImportOperation<Block, Backend>,
+        block: Hash,
+        justification: Option<Justification>,
+        notify: bool,
+    ) -> sp_blockchain::Result<()> {
+        self.client
+            .inner
+            .apply_finality(operation, block, justification, notify)
+    }
+
+    fn finalize_block(
+        &self,
+        block: Hash,
+        justification: Option<Justification>,
+        notify: bool,
+    ) -> sp_blockchain::Result<()> {
+        Finalizer::finalize_block(&*self.client.inner, block, justification, notify)
+    }
+}
@@ -0,0 +1,169 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use sc_client_api::{backend::Finalizer, BlockchainEvents};
+use sp_api::{ApiError, ApiExt, Core, ProvideRuntimeApi};
+use sp_blockchain::HeaderBackend;
+use sp_runtime::traits::Block as BlockT;
+use substrate_test_runtime::Block;
+use substrate_test_runtime_client::Backend;
+
+use super::{MockAnswers, MockRuntimeClient};
+use crate::{BlockTree, Client};
+
+sp_api::decl_runtime_apis! {
+    /// Validator set of an engine prototype
+    pub trait ValidatorSetApi {
+        fn validators() -> Vec<u64>;
+    }
+}
+
+#[derive(Clone)]
+struct MockApi {
+    validators: MockAnswers<Vec<u64>>,
+}
+
+sp_api::mock_impl_runtime_apis! {
+    impl ValidatorSetApi<Block> for MockApi {
+        #[advanced]
+        fn validators(&self, at: <Block as BlockT>::Hash) -> Result<Vec<u64>, ApiError> {
+            Ok(self.validators.get(at))
+        }
+    }
+}
+
+fn assert_client<C>(_: &C)
+where
+    C: BlockchainEvents<Block>
+        + HeaderBackend<Block>
+        + Finalizer<Block, Backend>
+        + ProvideRuntimeApi<Block>
+        + Send
+        + Sync,
+{
+}
+
+#[tokio::test]
+async fn per_block_answers() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let labels = BlockTree::parse("G-A-B")
+        .unwrap()
+        .build_client(&client)
+        .await;
+
+    let api = MockApi {
+        validators: MockAnswers::new(vec![1]),
+    };
+
+    let client = MockRuntimeClient::new(client, api);
+
+    client.api().validators.set(labels["A"], vec![1, 2]);
+
+    assert_eq!(
+        vec![1, 2],
+        client.runtime_api().validators(labels["A"]).unwrap()
+    );
+    assert_eq!(
+        vec![1],
+        client.runtime_api().validators(labels["B"]).unwrap()
+    );
+
+    client.api().validators.set_default(vec![3]);
+
+    assert_eq!(
+        vec![3],
+        client.runtime_api().validators(labels["G"]).unwrap()
+    );
+    assert_eq!(
+        vec![1, 2],
+        client.runtime_api().validators(labels["A"]).unwrap()
+    );
+}
+
+#[tokio::test]
+async fn delegate_to_client() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let labels = BlockTree::parse("G-A-B")
+        .unwrap()
+        .build_client(&client)
+        .await;
+
+    let client = MockRuntimeClient::new(
+        client,
+        MockApi {
+            validators: Default::default(),
+        },
+    );
+
+    assert_client(&client);
+
+    let version = client.runtime_api().version(labels["G"]).unwrap();
+
+    assert_eq!("test", version.spec_name.to_string());
+    assert_eq!(labels["B"], client.info().best_hash);
+
+    Finalizer::finalize_block(&client, labels["A"], None, true).unwrap();
+
+    assert_eq!(labels["A"], client.client().info().finalized_hash);
+}
+
+#[tokio::test]
+async fn mocked_api_info() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let genesis = client.info().genesis_hash;
+
+    let api = MockApi {
+        validators: Default::default(),
+    };
+
+    let unregistered = MockRuntimeClient::new(client.clone(), api.clone());
+
+    assert!(!unregistered
+        .runtime_api()
+        .has_api::<dyn ValidatorSetApi<Block>>(genesis)
+        .unwrap());
+
+    let client = MockRuntimeClient::new(client, api).with_api::<dyn ValidatorSetApi<Block>>();
+    let mut runtime_api = client.runtime_api();
+
+    assert!(runtime_api
+        .has_api::<dyn ValidatorSetApi<Block>>(genesis)
+        .unwrap());
+    assert!(runtime_api
+        .has_api_with::<dyn ValidatorSetApi<Block>, _>(genesis, |v| v == 1)
+        .unwrap());
+    assert_eq!(
+        Some(1),
+        runtime_api
+            .api_version::<dyn ValidatorSetApi<Block>>(genesis)
+            .unwrap()
+    );
+
+    // APIs which are not mocked are answered by the test runtime
+    assert!(runtime_api.has_api::<dyn Core<Block>>(genesis).unwrap());
+
+    // proof recording is a no-op
+    runtime_api.record_proof();
+
+    assert!(runtime_api.proof_recorder().is_none());
+    assert!(runtime_api.extract_proof().is_none());
+}
@@ -0,0 +1,194 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use codec::{Decode, Encode};
+use sc_consensus::{BlockImportParams, ForkChoiceStrategy, Verifier};
+use sp_core::{
+    sr25519::{Pair, Public, Signature},
+    Pair as _,
+};
+use sp_runtime::{
+    traits::{Block as BlockT, Header as HeaderT},
+    ConsensusEngineId, Digest, DigestItem,
+};
+use substrate_test_runtime::{Block, Hash};
+use substrate_test_runtime_client::AccountKeyring;
+
+use crate::Client;
+
+#[cfg(test)]
+#[path = "seal_tests.rs"]
+mod tests;
+
+/// Builds blocks carrying digest items of a single consensus engine.
+///
+/// PreRuntime and Consensus items are added to the block digest before execution. If a
+/// sealing key is set, the block is sealed with a signature over the pre-seal header hash.
+#[derive(Debug, Clone)]
+pub struct ConsensusBlockBuilder {
+    engine_id: ConsensusEngineId,
+    pre_runtime: Vec<Vec<u8>>,
+    consensus: Vec<Vec<u8>>,
+    seal: Option<AccountKeyring>,
+}
+
+impl ConsensusBlockBuilder {
+    pub fn new(engine_id: ConsensusEngineId) -> Self {
+        ConsensusBlockBuilder {
+            engine_id,
+            pre_runtime: Vec::new(),
+            consensus: Vec::new(),
+            seal: None,
+        }
+    }
+
+    /// Add a [`DigestItem::PreRuntime`] item
+    pub fn pre_runtime(mut self, data: Vec<u8>) -> Self {
+        self.pre_runtime.push(data);
+        self
+    }
+
+    /// Add a [`DigestItem::Consensus`] item
+    pub fn consensus(mut self, data: Vec<u8>) -> Self {
+        self.consensus.push(data);
+        self
+    }
+
+    /// Seal blocks using `key`
+    pub fn seal(mut self, key: AccountKeyring) -> Self {
+        self.seal = Some(key);
+        self
+    }
+
+    /// Build a block at `parent` using `client`. The block is not imported.
+    pub fn build(&self, client: &Client, parent: Hash) -> sp_blockchain::Result<Block> {
+        let logs = self
+            .pre_runtime
+            .iter()
+            .map(|data| DigestItem::PreRuntime(self.engine_id, data.clone()))
+            .chain(
+                self.consensus
+                    .iter()
+                    .map(|data| DigestItem::Consensus(self.engine_id, data.clone())),
+            )
+            .collect();
+
+        let block = client.new_block_at(parent, Digest { logs })?.build()?.block;
+
+        Ok(match self.seal {
+            Some(key) => seal_block(block, self.engine_id, &key.pair()),
+            None => block,
+        })
+    }
+}
+
+/// Seal `block` for `engine_id`, signing the pre-seal header hash with `pair`
+pub fn seal_block(block: Block, engine_id: ConsensusEngineId, pair: &Pair) -> Block {
+    let (mut header, body) = block.deconstruct();
+
+    let signature = pair.sign(header.hash().as_ref());
+
+    header
+        .digest_mut()
+        .push(DigestItem::Seal(engine_id, signature.encode()));
+
+    Block::new(header, body)
+}
+
+/// Verifier stripping and checking the seal of a consensus engine.
+///
+/// The last digest item of a block has to be a seal for the configured engine, signed
+/// by one of the configured authorities. The seal is moved to the post digests.
+#[derive(Debug, Clone)]
+pub struct SealVerifier {
+    engine_id: ConsensusEngineId,
+    authorities: Vec<Public>,
+    fork_choice: ForkChoiceStrategy,
+}
+
+impl SealVerifier {
+    pub fn new(engine_id: ConsensusEngineId, authorities: Vec<Public>) -> Self {
+        SealVerifier {
+            engine_id,
+            authorities,
+            fork_choice: ForkChoiceStrategy::LongestChain,
+        }
+    }
+
+    pub fn with_fork_choice(mut self, fork_choice: ForkChoiceStrategy) -> Self {
+        self.fork_choice = fork_choice;
+        self
+    }
+
+    /// Strip the seal from `header`, return the seal and the seal author.
+    ///
+    /// On error, `header` is left unchanged.
+    pub fn check<H>(&self, header: &mut H) -> Result<(DigestItem, Public), String>
+    where
+        H: HeaderT,
+    {
+        let hash = header.hash();
+
+        let signature = match header.digest().logs().last().and_then(DigestItem::as_seal) {
+            Some((id, mut data)) if id == self.engine_id => Signature::decode(&mut data)
+                .map_err(|err| format!("Header {:?} has a bad seal: {}", hash, err))?,
+            Some((id, _)) => {
+                return Err(format!(
+                    "Header {:?} sealed by unexpected engine {:?}",
+                    hash, id
+                ))
+            }
+            None => return Err(format!("Header {:?} is unsealed", hash)),
+        };
+
+        let seal = header.digest_mut().pop().expect("seal checked above");
+        let pre_hash = header.hash();
+
+        let author = self
+            .authorities
+            .iter()
+            .find(|author| Pair::verify(&signature, pre_hash.as_ref(), author))
+            .copied();
+
+        match author {
+            Some(author) => Ok((seal, author)),
+            None => {
+                header.digest_mut().push(seal);
+                Err(format!("Header {:?} has a bad seal signature", hash))
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<B> Verifier<B> for SealVerifier
+where
+    B: BlockT,
+{
+    async fn verify(
+        &mut self,
+        mut block: BlockImportParams<B, ()>,
+    ) -> Result<BlockImportParams<B, ()>, String> {
+        let hash = block.header.hash();
+        let (seal, _) = self.check(&mut block.header)?;
+
+        block.post_digests.push(seal);
+        block.post_hash = Some(hash);
+        block.fork_choice = Some(self.fork_choice);
+
+        Ok(block)
+    }
+}
@@ -0,0 +1,184 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use sc_consensus::{BlockImport, ImportResult, Verifier};
+use sp_consensus::BlockOrigin;
+use sp_runtime::{
+    traits::{Block as BlockT, Header as HeaderT},
+    ConsensusEngineId, DigestItem,
+};
+use substrate_test_runtime_client::AccountKeyring;
+
+use super::{ConsensusBlockBuilder, SealVerifier};
+use crate::{import_params, Client};
+
+const ENGINE_ID: ConsensusEngineId = *b"SEAL";
+
+#[tokio::test]
+async fn digest_items() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+
+    let block = ConsensusBlockBuilder::new(ENGINE_ID)
+        .pre_runtime(vec![1])
+        .consensus(vec![2])
+        .build(&client, client.info().genesis_hash)
+        .unwrap();
+
+    let logs = block.header().digest().logs();
+
+    assert_eq!(2, logs.len());
+    assert_eq!(DigestItem::PreRuntime(ENGINE_ID, vec![1]), logs[0]);
+    assert_eq!(DigestItem::Consensus(ENGINE_ID, vec![2]), logs[1]);
+}
+
+#[tokio::test]
+async fn seal_round_trip() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+
+    let block = ConsensusBlockBuilder::new(ENGINE_ID)
+        .pre_runtime(vec![1])
+        .seal(AccountKeyring::Alice)
+        .build(&client, client.info().genesis_hash)
+        .unwrap();
+
+    let hash = block.hash();
+
+    assert_eq!(2, block.header().digest().logs().len());
+    assert!(block.header().digest().logs()[1].as_seal().is_some());
+
+    let mut verifier = SealVerifier::new(
+        ENGINE_ID,
+        vec![AccountKeyring::Bob.public(), AccountKeyring::Alice.public()],
+    );
+
+    let params = verifier
+        .verify(import_params(BlockOrigin::NetworkBroadcast, block))
+        .await
+        .unwrap();
+
+    assert_eq!(1, params.header.digest().logs().len());
+    assert_eq!(1, params.post_digests.len());
+    assert_eq!(hash, params.post_hash());
+
+    let res = client.as_block_import().import_block(params).await.unwrap();
+
+    assert!(matches!(res, ImportResult::Imported(_)));
+    assert_eq!(hash, client.info().best_hash);
+}
+
+#[tokio::test]
+async fn unknown_author() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+
+    let block = ConsensusBlockBuilder::new(ENGINE_ID)
+        .seal(AccountKeyring::Charlie)
+        .build(&client, client.info().genesis_hash)
+        .unwrap();
+
+    let mut verifier = SealVerifier::new(ENGINE_ID, vec![AccountKeyring::Alice.public()]);
+
+    let err = verifier
+        .verify(import_params(BlockOrigin::NetworkBroadcast, block))
+        .await
+        .unwrap_err();
+
+    assert!(err.contains("bad seal signature"));
+}
+
+#[tokio::test]
+async fn bad_seal() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let genesis = client.info().genesis_hash;
+
+    let mut verifier = SealVerifier::new(ENGINE_ID, vec![AccountKeyring::Alice.public()]);
+
+    // unsealed
+    let block = ConsensusBlockBuilder::new(ENGINE_ID)
+        .build(&client, genesis)
+        .unwrap();
+
+    let err = verifier
+        .verify(import_params(BlockOrigin::NetworkBroadcast, block))
+        .await
+        .unwrap_err();
+
+    assert!(err.contains("unsealed"));
+
+    // sealed by a different engine
+    let block = ConsensusBlockBuilder::new(*b"OTHR")
+        .seal(AccountKeyring::Alice)
+        .build(&client, genesis)
+        .unwrap();
+
+    let err = verifier
+        .verify(import_params(BlockOrigin::NetworkBroadcast, block))
+        .await
+        .unwrap_err();
+
+    assert!(err.contains("unexpected engine"));
+
+    // tampered header
+    let block = ConsensusBlockBuilder::new(ENGINE_ID)
+        .seal(AccountKeyring::Alice)
+        .build(&client, genesis)
+        .unwrap();
+
+    let mut params = import_params(BlockOrigin::NetworkBroadcast, block);
+    params
+        .header
+        .digest_mut()
+        .logs
+        .insert(0, DigestItem::Other(vec![42]));
+
+    let err = verifier.verify(params).await.unwrap_err();
+
+    assert!(err.contains("bad seal signature"));
+}
+
+#[tokio::test]
+async fn check_keeps_header_on_error() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let genesis = client.info().genesis_hash;
+
+    let verifier = SealVerifier::new(ENGINE_ID, vec![AccountKeyring::Alice.public()]);
+
+    let blocks = [
+        // unsealed, the last digest item is not a seal
+        ConsensusBlockBuilder::new(ENGINE_ID).pre_runtime(vec![1]),
+        // sealed by a different engine
+        ConsensusBlockBuilder::new(*b"OTHR").seal(AccountKeyring::Alice),
+        // sealed by an unknown author
+        ConsensusBlockBuilder::new(ENGINE_ID).seal(AccountKeyring::Charlie),
+    ];
+
+    for builder in blocks {
+        let block = builder.build(&client, genesis).unwrap();
+        let mut header = block.header().clone();
+
+        assert!(verifier.check(&mut header).is_err());
+        assert_eq!(block.header(), &header);
+    }
+}
@@ -0,0 +1,412 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use codec::Encode;
+use parking_lot::Mutex;
+use sc_client_api::Backend as _;
+use sc_consensus::{BlockImportParams, ForkChoiceStrategy, Verifier};
+use sp_blockchain::{Backend as _, HeaderBackend};
+use sp_consensus::{Error, SelectChain};
+use sp_runtime::{
+    traits::{Header as HeaderT, NumberFor},
+    ConsensusEngineId, DigestItem,
+};
+use substrate_test_runtime::{Block, Hash, Header};
+
+use crate::Client;
+
+#[cfg(test)]
+#[path = "select_tests.rs"]
+mod tests;
+
+/// A [`SelectChain`] strategy, which can also decide whether a block to be imported
+/// becomes the new best block, as required by [`ForkChoiceStrategy::Custom`].
+pub trait ForkChoice: SelectChain<Block> {
+    /// Return whether the block with `header` becomes the new best block once imported.
+    /// The parent of the block has to be known already.
+    fn is_new_best(&self, header: &Header) -> Result<bool, Error>;
+}
+
+/// Return a digest item carrying the block `weight` for [`HeaviestChain`]
+pub fn weight_digest(engine_id: ConsensusEngineId, weight: u64) -> DigestItem {
+    DigestItem::PreRuntime(engine_id, weight.encode())
+}
+
+/// Select the chain with the highest accumulated weight since the last finalized block.
+///
+/// Block weights are read from the [`weight_digest`] items of `engine_id`. Blocks
+/// without such an item have weight zero. Ties are resolved in favour of the current
+/// best block and the chains extending it, resp. the first leaf.
+#[derive(Clone)]
+pub struct HeaviestChain {
+    client: Client,
+    engine_id: ConsensusEngineId,
+}
+
+impl HeaviestChain {
+    pub fn new(client: Client, engine_id: ConsensusEngineId) -> Self {
+        HeaviestChain { client, engine_id }
+    }
+
+    /// Return the weight of a single block
+    pub fn weight(&self, header: &Header) -> u64 {
+        header
+            .digest()
+            .logs()
+            .iter()
+            .find_map(|item| item.pre_runtime_try_to::<u64>(&self.engine_id))
+            .unwrap_or_default()
+    }
+
+    /// Return the weight of the chain ending at `hash`, counted from the last finalized
+    /// block. Return `None`, if the block does not descend from the last finalized block.
+    pub fn chain_weight(&self, hash: Hash) -> Result<Option<u64>, Error> {
+        let finalized = self.client.info().finalized_hash;
+        let mut weight = 0;
+
+        for header in ancestry(&self.client, hash)? {
+            if header.hash() == finalized {
+                return Ok(Some(weight));
+            }
+
+            weight += self.weight(&header);
+        }
+
+        Ok(None)
+    }
+
+    fn heaviest(&self) -> Result<(Hash, u64), Error> {
+        let best = self.client.info().best_hash;
+        let mut heaviest = (best, self.chain_weight(best)?.unwrap_or_default());
+
+        for leaf in leaves(&self.client)? {
+            if let Some(weight) = self.chain_weight(leaf)? {
+                // on a tie, prefer a leaf extending the current best block
+                let extends_best = weight == heaviest.1
+                    && heaviest.0 == best
+                    && extends(&self.client, leaf, best)?;
+
+                if weight > heaviest.1 || extends_best {
+                    heaviest = (leaf, weight);
+                }
+            }
+        }
+
+        Ok(heaviest)
+    }
+}
+
+#[async_trait::async_trait]
+impl SelectChain<Block> for HeaviestChain {
+    async fn leaves(&self) -> Result<Vec<Hash>, Error> {
+        leaves(&self.client)
+    }
+
+    async fn best_chain(&self) -> Result<Header, Error> {
+        header(&self.client, self.heaviest()?.0)
+    }
+}
+
+impl ForkChoice for HeaviestChain {
+    fn is_new_best(&self, header: &Header) -> Result<bool, Error> {
+        let parent = *header.parent_hash();
+
+        let weight = match self.chain_weight(parent)? {
+            Some(weight) => weight + self.weight(header),
+            None => return Ok(false),
+        };
+
+        let heaviest = self.heaviest()?.1;
+
+        // descendants of the current best block are never considered lighter
+        Ok(weight > heaviest
+            || (weight == heaviest && extends(&self.client, parent, self.client.info().best_hash)?))
+    }
+}
+
+/// Select the longest chain, but never more than `max` blocks past the last finalized
+/// block, similar to a voting rule.
+#[derive(Clone)]
+pub struct CappedLongestChain {
+    client: Client,
+    max: NumberFor<Block>,
+}
+
+impl CappedLongestChain {
+    pub fn new(client: Client, max: NumberFor<Block>) -> Self {
+        CappedLongestChain { client, max }
+    }
+
+    fn cap(&self) -> NumberFor<Block> {
+        self.client.info().finalized_number + self.max
+    }
+
+    // Return the ancestor of `hash` at the cap, or `hash` itself if below the cap
+    fn capped(&self, hash: Hash) -> Result<Header, Error> {
+        let cap = self.cap();
+
+        ancestry(&self.client, hash)?
+            .into_iter()
+            .find(|h| *h.number() <= cap)
+            .ok_or_else(|| Error::ChainLookup(format!("No ancestor of {} below cap", hash)))
+    }
+}
+
+#[async_trait::async_trait]
+impl SelectChain<Block> for CappedLongestChain {
+    async fn leaves(&self) -> Result<Vec<Hash>, Error> {
+        leaves(&self.client)
+    }
+
+    async fn best_chain(&self) -> Result<Header, Error> {
+        let best = self.client.chain().best_chain().await?;
+        self.capped(best.hash())
+    }
+
+    async fn finality_target(
+        &self,
+        base: Hash,
+        max: Option<NumberFor<Block>>,
+    ) -> Result<Hash, Error> {
+        let best = self.best_chain().await?;
+        let ancestry = ancestry(&self.client, best.hash())?;
+
+        let base_pos = match ancestry.iter().position(|h| h.hash() == base) {
+            Some(pos) => pos,
+            None => return Ok(base),
+        };
+
+        // highest ancestor of the best block not above `max`, but not below `base`
+        Ok(ancestry[..=base_pos]
+            .iter()
+            .find(|h| max.map_or(true, |max| *h.number() <= max))
+            .map_or(base, |h| h.hash()))
+    }
+}
+
+impl ForkChoice for CappedLongestChain {
+    fn is_new_best(&self, header: &Header) -> Result<bool, Error> {
+        let best = self.capped(self.client.info().best_hash)?;
+        Ok(*header.number() <= self.cap() && header.number() > best.number())
+    }
+}
+
+/// Always select a fixed best block, regardless of any fork choice rule
+#[derive(Clone)]
+pub struct FixedBest {
+    client: Client,
+    best: Arc<Mutex<Hash>>,
+}
+
+impl FixedBest {
+    pub fn new(client: Client, best: Hash) -> Self {
+        FixedBest {
+            client,
+            best: Arc::new(Mutex::new(best)),
+        }
+    }
+
+    /// Select `best` from now on
+    pub fn set(&self, best: Hash) {
+        *self.best.lock() = best;
+    }
+}
+
+#[async_trait::async_trait]
+impl SelectChain<Block> for FixedBest {
+    async fn leaves(&self) -> Result<Vec<Hash>, Error> {
+        leaves(&self.client)
+    }
+
+    async fn best_chain(&self) -> Result<Header, Error> {
+        header(&self.client, *self.best.lock())
+    }
+}
+
+impl ForkChoice for FixedBest {
+    fn is_new_best(&self, header: &Header) -> Result<bool, Error> {
+        Ok(header.hash() == *self.best.lock())
+    }
+}
+
+/// GHOST-style fork choice.
+///
+/// Starting at the last finalized block, repeatedly select the child with the largest
+/// subtree, counted in blocks, until a leaf is reached. Ties are resolved in favour of
+/// the lowest block hash.
+#[derive(Clone)]
+pub struct GhostChain {
+    client: Client,
+}
+
+impl GhostChain {
+    pub fn new(client: Client) -> Self {
+        GhostChain { client }
+    }
+
+    // Return the GHOST head, optionally including a block not yet imported
+    fn head(&self, extra: Option<(Hash, Hash)>) -> Result<Hash, Error> {
+        let finalized = self.client.info().finalized_hash;
+
+        let mut parents = HashMap::new();
+        let mut tips: Vec<(Hash, Hash)> = Vec::new();
+
+        for leaf in leaves(&self.client)? {
+            let ancestry = ancestry(&self.client, leaf)?;
+
+            if let Some(pos) = ancestry.iter().position(|h| h.hash() == finalized) {
+                for header in ancestry[..pos].iter() {
+                    parents.insert(header.hash(), *header.parent_hash());
+                }
+            }
+        }
+
+        if let Some((hash, parent)) = extra {
+            if parent == finalized || parents.contains_key(&parent) {
+                tips.push((hash, parent));
+            }
+        }
+
+        parents.extend(tips);
+
+        let mut weights: HashMap<Hash, usize> = HashMap::new();
+
+        for hash in parents.keys() {
+            let mut current = *hash;
+
+            while current != finalized {
+                *weights.entry(current).or_default() += 1;
+                current = parents[&current];
+            }
+        }
+
+        let mut head = finalized;
+
+        loop {
+            let children: HashSet<_> = parents
+                .iter()
+                .filter(|(_, p)| **p == head)
+                .map(|(h, _)| *h)
+                .collect();
+
+            match children
+                .into_iter()
+                .max_by(|a, b| weights[a].cmp(&weights[b]).then_with(|| b.cmp(a)))
+            {
+                Some(child) => head = child,
+                None => return Ok(head),
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl SelectChain<Block> for GhostChain {
+    async fn leaves(&self) -> Result<Vec<Hash>, Error> {
+        leaves(&self.client)
+    }
+
+    async fn best_chain(&self) -> Result<Header, Error> {
+        header(&self.client, self.head(None)?)
+    }
+}
+
+impl ForkChoice for GhostChain {
+    fn is_new_best(&self, header: &Header) -> Result<bool, Error> {
+        let hash = header.hash();
+        Ok(self.head(Some((hash, *header.parent_hash())))? == hash)
+    }
+}
+
+/// Wraps a verifier and sets [`ForkChoiceStrategy::Custom`] according to a [`ForkChoice`]
+pub struct ForkChoiceVerifier<V, FC> {
+    inner: V,
+    fork_choice: FC,
+}
+
+impl<V, FC> ForkChoiceVerifier<V, FC> {
+    pub fn new(inner: V, fork_choice: FC) -> Self {
+        ForkChoiceVerifier { inner, fork_choice }
+    }
+}
+
+#[async_trait::async_trait]
+impl<V, FC> Verifier<Block> for ForkChoiceVerifier<V, FC>
+where
+    V: Verifier<Block>,
+    FC: ForkChoice,
+{
+    async fn verify(
+        &mut self,
+        block: BlockImportParams<Block, ()>,
+    ) -> Result<BlockImportParams<Block, ()>, String> {
+        let mut block = self.inner.verify(block).await?;
+
+        let is_new_best = self
+            .fork_choice
+            .is_new_best(&block.header)
+            .map_err(|err| format!("Fork choice failed: {}", err))?;
+
+        block.fork_choice = Some(ForkChoiceStrategy::Custom(is_new_best));
+
+        Ok(block)
+    }
+}
+
+fn leaves(client: &Client) -> Result<Vec<Hash>, Error> {
+    client
+        .backend
+        .blockchain()
+        .leaves()
+        .map_err(|err| Error::ChainLookup(err.to_string()))
+}
+
+fn header(client: &Client, hash: Hash) -> Result<Header, Error> {
+    client
+        .inner
+        .header(hash)
+        .ok()
+        .flatten()
+        .ok_or_else(|| Error::ChainLookup(format!("Unknown block {}", hash)))
+}
+
+// Return whether block `hash` is `ancestor` or one of its descendants
+fn extends(client: &Client, hash: Hash, ancestor: Hash) -> Result<bool, Error> {
+    Ok(ancestry(client, hash)?.iter().any(|h| h.hash() == ancestor))
+}
+
+// Return the headers from `hash` back to the last finalized block (inclusive), or to
+// the block at the last finalized number, if `hash` does not descend from it.
+fn ancestry(client: &Client, hash: Hash) -> Result<Vec<Header>, Error> {
+    let finalized = client.info().finalized_number;
+    let mut headers = Vec::new();
+    let mut current = header(client, hash)?;
+
+    while *current.number() > finalized {
+        let parent = header(client, *current.parent_hash())?;
+        headers.push(current);
+        current = parent;
+    }
+
+    headers.push(current);
+
+    Ok(headers)
+}
@@ -0,0 +1,250 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use codec::Encode;
+use sc_client_api::BlockBackend;
+use sc_consensus::{BlockImport, Verifier};
+use sp_consensus::{BlockOrigin, SelectChain};
+use sp_runtime::{
+    traits::{Block as BlockT, Header as HeaderT},
+    ConsensusEngineId,
+};
+use substrate_test_runtime::{Block, Hash};
+use substrate_test_runtime_client::ClientBlockImportExt;
+
+use super::{
+    weight_digest, CappedLongestChain, FixedBest, ForkChoice, ForkChoiceVerifier, GhostChain,
+    HeaviestChain,
+};
+use crate::{import_params, BlockTree, Client, ConsensusBlockBuilder, Labels, PassThroughVerifier};
+
+const ENGINE_ID: ConsensusEngineId = *b"WGHT";
+
+// Build and import a block with `weight` on top of `parent`
+async fn weighted(client: &Client, parent: Hash, weight: u64) -> Block {
+    let block = ConsensusBlockBuilder::new(ENGINE_ID)
+        .pre_runtime(weight.encode())
+        .build(client, parent)
+        .unwrap();
+
+    client
+        .as_inner()
+        .import(BlockOrigin::Own, block.clone())
+        .await
+        .unwrap();
+
+    block
+}
+
+// Import the blocks with `labels` from `source` into `client`, using `fork_choice`
+async fn import_with<FC>(
+    client: &Client,
+    fork_choice: FC,
+    source: &Client,
+    labels: &Labels<Hash>,
+    order: &[&str],
+) where
+    FC: ForkChoice + 'static,
+{
+    let mut verifier = ForkChoiceVerifier::new(PassThroughVerifier::new(false), fork_choice);
+    let mut import = client.as_block_import();
+
+    for label in order {
+        let block = source.inner.block(labels[*label]).unwrap().unwrap().block;
+        let params = import_params(BlockOrigin::NetworkBroadcast, block);
+
+        let params = verifier.verify(params).await.unwrap();
+        import.import_block(params).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn heaviest_chain() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let heaviest = HeaviestChain::new(client.clone(), ENGINE_ID);
+    let genesis = client.info().genesis_hash;
+
+    let a = weighted(&client, genesis, 1).await;
+    let b = weighted(&client, a.hash(), 1).await;
+    let c = weighted(&client, b.hash(), 1).await;
+    let x = weighted(&client, a.hash(), 5).await;
+
+    assert_eq!(5, heaviest.weight(x.header()));
+    assert_eq!(Some(3), heaviest.chain_weight(c.hash()).unwrap());
+    assert_eq!(Some(6), heaviest.chain_weight(x.hash()).unwrap());
+
+    // longest chain and heaviest chain differ
+    assert_eq!(c.hash(), client.info().best_hash);
+    assert_eq!(x.hash(), heaviest.best_chain().await.unwrap().hash());
+
+    assert_eq!(
+        weight_digest(ENGINE_ID, 5),
+        x.header().digest().logs()[0].clone()
+    );
+}
+
+#[tokio::test]
+async fn heaviest_chain_custom_fork_choice() {
+    sp_tracing::try_init_simple();
+
+    let source = Client::new();
+    let genesis = source.info().genesis_hash;
+
+    let a = weighted(&source, genesis, 1).await;
+    let b = weighted(&source, a.hash(), 1).await;
+    let x = weighted(&source, genesis, 3).await;
+
+    let client = Client::new();
+    let mut verifier = ForkChoiceVerifier::new(
+        PassThroughVerifier::new(false),
+        HeaviestChain::new(client.clone(), ENGINE_ID),
+    );
+    let mut import = client.as_block_import();
+
+    for block in [a, b, x.clone()] {
+        let params = import_params(BlockOrigin::NetworkBroadcast, block);
+
+        let params = verifier.verify(params).await.unwrap();
+        import.import_block(params).await.unwrap();
+    }
+
+    assert_eq!(x.hash(), client.info().best_hash);
+}
+
+#[tokio::test]
+async fn heaviest_chain_unweighted() {
+    sp_tracing::try_init_simple();
+
+    let source = Client::new();
+    let labels = BlockTree::parse("G-A-B")
+        .unwrap()
+        .build_client(&source)
+        .await;
+
+    let client = Client::new();
+    let heaviest = HeaviestChain::new(client.clone(), ENGINE_ID);
+
+    import_with(&client, heaviest.clone(), &source, &labels, &["A", "B"]).await;
+
+    // blocks without weight extending the best block still become the new best block
+    assert_eq!(labels["B"], client.info().best_hash);
+    assert_eq!(labels["B"], heaviest.best_chain().await.unwrap().hash());
+}
+
+#[tokio::test]
+async fn capped_longest_chain() {
+    sp_tracing::try_init_simple();
+
+    let source = Client::new();
+    let labels = BlockTree::parse("G-A-B-C-D")
+        .unwrap()
+        .build_client(&source)
+        .await;
+
+    let capped = CappedLongestChain::new(source.clone(), 2);
+
+    assert_eq!(labels["B"], capped.best_chain().await.unwrap().hash());
+    assert_eq!(
+        labels["B"],
+        capped.finality_target(labels["G"], None).await.unwrap()
+    );
+    assert_eq!(
+        labels["A"],
+        capped.finality_target(labels["G"], Some(1)).await.unwrap()
+    );
+
+    let client = Client::new();
+    let fork_choice = CappedLongestChain::new(client.clone(), 2);
+
+    import_with(
+        &client,
+        fork_choice,
+        &source,
+        &labels,
+        &["A", "B", "C", "D"],
+    )
+    .await;
+
+    assert_eq!(labels["B"], client.info().best_hash);
+    assert_eq!(2, client.info().best_number);
+}
+
+#[tokio::test]
+async fn fixed_best() {
+    sp_tracing::try_init_simple();
+
+    let source = Client::new();
+    let labels = BlockTree::parse("G-A-B-C; G-X")
+        .unwrap()
+        .build_client(&source)
+        .await;
+
+    let fixed = FixedBest::new(source.clone(), labels["X"]);
+
+    assert_eq!(labels["X"], fixed.best_chain().await.unwrap().hash());
+
+    fixed.set(labels["B"]);
+
+    assert_eq!(labels["B"], fixed.best_chain().await.unwrap().hash());
+
+    let client = Client::new();
+    let fork_choice = FixedBest::new(client.clone(), labels["X"]);
+
+    import_with(
+        &client,
+        fork_choice,
+        &source,
+        &labels,
+        &["A", "B", "C", "X"],
+    )
+    .await;
+
+    assert_eq!(labels["X"], client.info().best_hash);
+}
+
+#[tokio::test]
+async fn ghost_chain() {
+    sp_tracing::try_init_simple();
+
+    let source = Client::new();
+    let labels = BlockTree::parse("G-A-B-C; G-X-Y; X-Z-W")
+        .unwrap()
+        .build_client(&source)
+        .await;
+
+    let ghost = GhostChain::new(source.clone());
+    let head = ghost.best_chain().await.unwrap();
+
+    // `X` has the larger subtree, `Z` the larger subtree of the children of `X`
+    assert_eq!(labels["C"], source.info().best_hash);
+    assert_eq!(labels["W"], head.hash());
+
+    let client = Client::new();
+    let fork_choice = GhostChain::new(client.clone());
+
+    import_with(
+        &client,
+        fork_choice,
+        &source,
+        &labels,
+        &["A", "B", "C", "X", "Y", "Z", "W"],
+    )
+    .await;
+
+    assert_eq!(labels["W"], client.info().best_hash);
+}
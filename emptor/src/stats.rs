@@ -0,0 +1,48 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::time::Duration;
+
+/// Block import timing statistics
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportStats {
+    /// Number of block imports
+    pub imports: usize,
+    /// Number of block imports, which applied precomputed storage changes
+    pub with_changes: usize,
+    /// Total time spent importing blocks
+    pub total: Duration,
+    /// Fastest block import
+    pub min: Option<Duration>,
+    /// Slowest block import
+    pub max: Option<Duration>,
+}
+
+impl ImportStats {
+    /// Record a block import, which took `elapsed`
+    pub fn record(&mut self, elapsed: Duration, with_changes: bool) {
+        self.imports += 1;
+        self.with_changes += with_changes as usize;
+        self.total += elapsed;
+        self.min = Some(self.min.map_or(elapsed, |min| min.min(elapsed)));
+        self.max = Some(self.max.map_or(elapsed, |max| max.max(elapsed)));
+    }
+
+    /// Return the mean block import time
+    pub fn mean(&self) -> Option<Duration> {
+        (self.imports > 0).then(|| self.total / self.imports as u32)
+    }
+}
@@ -0,0 +1,387 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{collections::HashMap, ops::Index};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use sc_client_api::Backend;
+use sp_consensus::BlockOrigin;
+use sp_runtime::{
+    generic::{BlockId, Digest, DigestItem},
+    traits::{Block as BlockT, Hash as HashT, HashFor},
+    ConsensusEngineId, SaturatedConversion,
+};
+use substrate_test_runtime_client::runtime::Hash;
+
+use crate::{backend, Client};
+
+#[cfg(test)]
+#[path = "tree_tests.rs"]
+mod tests;
+
+/// Engine id used for justifications of justified (`#`) blocks, unless configured otherwise.
+pub const DEFAULT_ENGINE_ID: ConsensusEngineId = *b"EMPT";
+
+/// How a block of the tree should be marked once all blocks have been imported
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mark {
+    /// Finalize the block without a justification, denoted by a trailing `!`
+    Finalized,
+    /// Finalize the block with a justification, denoted by a trailing `#`
+    Justified,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    label: String,
+    parent: Option<String>,
+    number: u64,
+}
+
+/// A declarative block tree.
+///
+/// The tree is described by a list of chains separated by `;`. Blocks within a chain
+/// are separated by `-`. The first block of the first chain is the genesis block, every
+/// other chain has to start at an already known block. For example
+///
+/// ```text
+/// G-A-B-C; B-D!-E; A-F#
+/// ```
+///
+/// describes a tree with forks at `A` and `B`, where `D` gets finalized and `F` gets
+/// finalized with a justification. Note that marks are applied in order of appearance,
+/// after all blocks have been imported.
+#[derive(Debug, Clone)]
+pub struct BlockTree {
+    nodes: Vec<Node>,
+    marks: Vec<(String, Mark)>,
+    engine_id: ConsensusEngineId,
+}
+
+impl BlockTree {
+    /// Parse a block tree from `spec`
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut nodes: Vec<Node> = Vec::new();
+        let mut marks = Vec::new();
+
+        for chain in spec.split(';').map(str::trim).filter(|c| !c.is_empty()) {
+            let mut parent: Option<(String, u64)> = None;
+            let mut chain_nodes = Vec::new();
+
+            for (i, label) in chain.split('-').map(str::trim).enumerate() {
+                let (label, mark) = match label.strip_suffix('!') {
+                    Some(label) => (label, Some(Mark::Finalized)),
+                    None => match label.strip_suffix('#') {
+                        Some(label) => (label, Some(Mark::Justified)),
+                        None => (label, None),
+                    },
+                };
+
+                if label.is_empty() || !label.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                    return Err(format!("invalid label `{}` in chain `{}`", label, chain));
+                }
+
+                let known = nodes
+                    .iter()
+                    .chain(chain_nodes.iter())
+                    .find(|n| n.label == label)
+                    .map(|n| n.number);
+
+                let number = match (i, known) {
+                    // fork point of a chain
+                    (0, Some(number)) => number,
+                    (0, None) if !nodes.is_empty() => {
+                        return Err(format!(
+                            "unknown fork point `{}` in chain `{}`",
+                            label, chain
+                        ))
+                    }
+                    (_, Some(_)) => return Err(format!("duplicate label `{}`", label)),
+                    (_, None) => {
+                        let number = parent.as_ref().map_or(0, |(_, n)| n + 1);
+
+                        chain_nodes.push(Node {
+                            label: label.to_string(),
+                            parent: parent.as_ref().map(|(l, _)| l.clone()),
+                            number,
+                        });
+
+                        number
+                    }
+                };
+
+                if let Some(mark) = mark {
+                    marks.push((label.to_string(), mark));
+                }
+
+                parent = Some((label.to_string(), number));
+            }
+
+            nodes.append(&mut chain_nodes);
+        }
+
+        if nodes.is_empty() {
+            return Err("empty block tree".to_string());
+        }
+
+        Ok(BlockTree {
+            nodes,
+            marks,
+            engine_id: DEFAULT_ENGINE_ID,
+        })
+    }
+
+    /// Use `engine_id` for justifications of justified blocks
+    pub fn with_engine_id(mut self, engine_id: ConsensusEngineId) -> Self {
+        self.engine_id = engine_id;
+        self
+    }
+
+    /// Generate a random block tree with genesis `G` and blocks `B1` up to `B<blocks>`.
+    ///
+    /// Each block extends one of the most recent blocks, which results in forks of
+    /// varying length. A random block is finalized, together with its ancestors. The same
+    /// `seed` always generates the same tree.
+    pub fn random(seed: u64, blocks: usize) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let mut nodes = vec![Node {
+            label: "G".to_string(),
+            parent: None,
+            number: 0,
+        }];
+
+        for i in 1..=blocks {
+            // prefer recent blocks as parents, to get longer chains
+            let parent = rng.gen_range(nodes.len().saturating_sub(4)..nodes.len());
+            let (label, number) = (nodes[parent].label.clone(), nodes[parent].number);
+
+            nodes.push(Node {
+                label: format!("B{}", i),
+                parent: Some(label),
+                number: number + 1,
+            });
+        }
+
+        let mut marks = Vec::new();
+
+        if blocks > 0 {
+            let mut node = &nodes[rng.gen_range(1..nodes.len())];
+
+            // finalize ancestors first, as required by raw backends
+            while let Some(ref parent) = node.parent {
+                marks.push((node.label.clone(), Mark::Finalized));
+                node = nodes
+                    .iter()
+                    .find(|n| n.label == *parent)
+                    .expect("known parent");
+            }
+
+            marks.reverse();
+        }
+
+        BlockTree {
+            nodes,
+            marks,
+            engine_id: DEFAULT_ENGINE_ID,
+        }
+    }
+
+    /// Insert the block tree as headers into a raw `backend`.
+    ///
+    /// Block hashes are made unique by deriving the `extrinsics_root` from the block label.
+    /// Note that the raw backend only supports sequential finalization, i.e. the parent of
+    /// a finalized block has to be marked as finalized as well.
+    pub fn build_backend<B>(&self, backend: &sc_client_db::Backend<B>) -> Labels<B::Hash>
+    where
+        B: BlockT,
+    {
+        let mut labels = Labels::default();
+
+        for node in self.nodes.iter() {
+            let parent = node
+                .parent
+                .as_ref()
+                .map_or(Default::default(), |p| labels[p.as_str()]);
+
+            let hash = backend::insert_header(
+                backend,
+                node.number.saturated_into(),
+                parent,
+                None,
+                <HashFor<B> as HashT>::hash(node.label.as_bytes()),
+            );
+
+            labels.insert(&node.label, hash);
+        }
+
+        for (label, mark) in self.marks.iter() {
+            backend
+                .finalize_block(
+                    BlockId::Hash(labels[label.as_str()]),
+                    self.justification(label, *mark),
+                )
+                .expect("finalize block failed");
+        }
+
+        labels
+    }
+
+    /// Import the block tree into `client`.
+    ///
+    /// The genesis label maps to the client's genesis block. Block hashes are made unique
+    /// by adding the block label as a [`DigestItem::Other`] to the block digest.
+    pub async fn build_client(&self, client: &Client) -> Labels<Hash> {
+        let mut labels = Labels::default();
+
+        for node in self.nodes.iter() {
+            let parent = match node.parent {
+                Some(ref parent) => labels[parent.as_str()],
+                None => {
+                    labels.insert(&node.label, client.info().genesis_hash);
+                    continue;
+                }
+            };
+
+            let digest = Digest {
+                logs: vec![DigestItem::Other(node.label.as_bytes().to_vec())],
+            };
+
+            let built = client
+                .new_block_at(parent, digest)
+                .expect("failed to create a new block")
+                .build()
+                .expect("failed to build block");
+
+            let hash = built.block.hash();
+
+            client
+                .import_built_block(BlockOrigin::File, built)
+                .await
+                .expect("block import failed");
+
+            labels.insert(&node.label, hash);
+        }
+
+        for (label, mark) in self.marks.iter() {
+            client
+                .finalize_block(
+                    BlockId::Hash(labels[label.as_str()]),
+                    self.justification(label, *mark),
+                    true,
+                )
+                .expect("finalize block failed");
+        }
+
+        labels
+    }
+
+    /// Return the labels of all leaves, which do not descend from the last finalized block,
+    /// but are not below its block number.
+    ///
+    /// Backends only prune leaves below the finalized block number, so these leaves are
+    /// expected to remain in the leaf set.
+    #[cfg(test)]
+    pub(crate) fn displaced_leaves(&self) -> Vec<&str> {
+        let node = |label: &str| {
+            self.nodes
+                .iter()
+                .find(|n| n.label == label)
+                .expect("known label")
+        };
+
+        let finalized = self
+            .marks
+            .iter()
+            .map(|(label, _)| node(label))
+            .max_by_key(|n| n.number)
+            .unwrap_or(&self.nodes[0]);
+
+        self.nodes
+            .iter()
+            .filter(|n| n.number >= finalized.number)
+            .filter(|n| {
+                !self
+                    .nodes
+                    .iter()
+                    .any(|c| c.parent.as_deref() == Some(n.label.as_str()))
+            })
+            .filter(|n| {
+                let mut ancestor = *n;
+
+                while ancestor.number > finalized.number {
+                    ancestor = node(ancestor.parent.as_deref().expect("known parent"));
+                }
+
+                ancestor.label != finalized.label
+            })
+            .map(|n| n.label.as_str())
+            .collect()
+    }
+
+    fn justification(&self, label: &str, mark: Mark) -> Option<sp_runtime::Justification> {
+        match mark {
+            Mark::Finalized => None,
+            Mark::Justified => Some((self.engine_id, label.as_bytes().to_vec())),
+        }
+    }
+}
+
+/// Mapping of block tree labels to block hashes
+#[derive(Debug, Clone)]
+pub struct Labels<H> {
+    hashes: HashMap<String, H>,
+}
+
+impl<H> Default for Labels<H> {
+    fn default() -> Self {
+        Labels {
+            hashes: HashMap::new(),
+        }
+    }
+}
+
+impl<H> Labels<H>
+where
+    H: Copy + PartialEq,
+{
+    /// Return the hash of the block labeled `label`
+    pub fn get(&self, label: &str) -> Option<H> {
+        self.hashes.get(label).copied()
+    }
+
+    /// Return the label of the block with `hash`
+    pub fn label(&self, hash: &H) -> Option<&str> {
+        self.hashes
+            .iter()
+            .find(|(_, h)| *h == hash)
+            .map(|(l, _)| l.as_str())
+    }
+
+    fn insert(&mut self, label: &str, hash: H) {
+        self.hashes.insert(label.to_string(), hash);
+    }
+}
+
+impl<H> Index<&str> for Labels<H> {
+    type Output = H;
+
+    fn index(&self, label: &str) -> &Self::Output {
+        self.hashes
+            .get(label)
+            .unwrap_or_else(|| panic!("unknown block label `{}`", label))
+    }
+}
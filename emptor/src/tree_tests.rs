@@ -0,0 +1,125 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use sc_client_api::BlockBackend;
+use sp_blockchain::Backend as ChainBackend;
+use sp_runtime::Justifications;
+
+use super::BlockTree;
+use crate::{backend::Block, Client};
+
+#[test]
+fn parse_errors() {
+    assert!(BlockTree::parse("").is_err());
+    assert!(BlockTree::parse("G-A; X-B").is_err());
+    assert!(BlockTree::parse("G-A-B; A-B").is_err());
+    assert!(BlockTree::parse("G-A-?").is_err());
+    assert!(BlockTree::parse("G-A-B-C; B-D-E; A-F").is_ok());
+}
+
+#[test]
+fn backend_tree() {
+    let backend = sc_client_db::Backend::<Block>::new_test(10, 10);
+
+    let labels = BlockTree::parse("G-A-B-C; B-D-E; A-F")
+        .unwrap()
+        .build_backend(&backend);
+
+    assert_eq!(
+        vec![labels["E"], labels["C"], labels["F"]],
+        backend.blockchain().leaves().unwrap()
+    );
+
+    assert_eq!(Some("D"), labels.label(&labels["D"]));
+    assert_eq!(None, labels.get("X"));
+}
+
+#[test]
+fn backend_prunning_leaves() {
+    let backend = sc_client_db::Backend::<Block>::new_test(10, 10);
+
+    let labels = BlockTree::parse("G-A!-AA!; G-B-BA; B-BB; G-C")
+        .unwrap()
+        .build_backend(&backend);
+
+    // leaves at the same height stay, leaves at lower height get pruned (`C`)
+    assert_eq!(
+        vec![labels["AA"], labels["BA"], labels["BB"]],
+        backend.blockchain().leaves().unwrap()
+    );
+}
+
+#[tokio::test]
+async fn client_tree() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+
+    let labels = BlockTree::parse("G-A-B!-C; B-D-E; A-F")
+        .unwrap()
+        .build_client(&client)
+        .await;
+
+    let info = client.info();
+
+    assert_eq!(labels["G"], info.genesis_hash);
+    assert_eq!(labels["E"], info.best_hash);
+    assert_eq!(labels["B"], info.finalized_hash);
+    assert_eq!(2, info.finalized_number);
+}
+
+#[tokio::test]
+async fn client_justified() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+
+    let labels = BlockTree::parse("G-A-B#")
+        .unwrap()
+        .with_engine_id(*b"SMPL")
+        .build_client(&client)
+        .await;
+
+    assert_eq!(labels["B"], client.info().finalized_hash);
+
+    assert_eq!(
+        Some(Justifications::from((*b"SMPL", b"B".to_vec()))),
+        client.as_inner().justifications(labels["B"]).unwrap()
+    );
+}
+
+#[test]
+fn random_tree() {
+    let tree = BlockTree::random(7, 20);
+
+    assert_eq!(tree.nodes.len(), 21);
+    assert_eq!(tree.nodes[0].label, "G");
+    assert_eq!(
+        format!("{:?}", tree),
+        format!("{:?}", BlockTree::random(7, 20))
+    );
+
+    // finalized blocks form a chain starting at genesis
+    let mut parent = "G".to_string();
+
+    for (label, _) in tree.marks.iter() {
+        let node = tree.nodes.iter().find(|n| n.label == *label).unwrap();
+        assert_eq!(node.parent.as_ref(), Some(&parent));
+        parent = label.clone();
+    }
+
+    assert!(!tree.marks.is_empty());
+}
@@ -0,0 +1,189 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{fmt, time::Duration};
+
+use futures::{FutureExt, StreamExt};
+use sc_client_api::{Backend as _, BlockchainEvents};
+use sc_executor::NativeExecutionDispatch;
+use sp_blockchain::{Backend as _, HeaderBackend, Info};
+use sp_runtime::traits::{Block as BlockT, NumberFor};
+
+use crate::Client;
+
+#[cfg(test)]
+#[path = "wait_tests.rs"]
+mod tests;
+
+/// Error returned by the [`Client`] waiters
+#[derive(Debug)]
+pub enum WaitError<B: BlockT> {
+    /// Condition not met within the timeout
+    Timeout {
+        condition: String,
+        info: Info<B>,
+        leaves: Vec<B::Hash>,
+    },
+    /// Client notification streams have been closed
+    Closed {
+        condition: String,
+        info: Info<B>,
+        leaves: Vec<B::Hash>,
+    },
+}
+
+impl<B: BlockT> fmt::Display for WaitError<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (reason, condition, info, leaves) = match self {
+            WaitError::Timeout {
+                condition,
+                info,
+                leaves,
+            } => ("timeout", condition, info, leaves),
+            WaitError::Closed {
+                condition,
+                info,
+                leaves,
+            } => ("streams closed", condition, info, leaves),
+        };
+
+        write!(
+            f,
+            "{} waiting for {}: best #{} ({:?}), finalized #{} ({:?}), leaves {:?}",
+            reason,
+            condition,
+            info.best_number,
+            info.best_hash,
+            info.finalized_number,
+            info.finalized_hash,
+            leaves
+        )
+    }
+}
+
+impl<B: BlockT> std::error::Error for WaitError<B> {}
+
+impl<B, D, RA> Client<B, D, RA>
+where
+    B: BlockT,
+    D: NativeExecutionDispatch + 'static,
+    RA: Send + Sync,
+{
+    /// Wait until the best block number is at least `number`, return the best block hash
+    pub async fn wait_for_best(
+        &self,
+        number: NumberFor<B>,
+        timeout: Duration,
+    ) -> Result<B::Hash, WaitError<B>> {
+        self.wait(format!("best block #{}", number), timeout, || {
+            let info = self.info();
+            (info.best_number >= number).then_some(info.best_hash)
+        })
+        .await
+    }
+
+    /// Wait until the block with `hash` has been finalized, explicitly or implicitly
+    pub async fn wait_for_finalized(
+        &self,
+        hash: B::Hash,
+        timeout: Duration,
+    ) -> Result<(), WaitError<B>> {
+        self.wait(format!("finalized block {:?}", hash), timeout, || {
+            let number = self.inner.number(hash).ok().flatten()?;
+            let finalized = self.info().finalized_number;
+            let canonical = self.inner.hash(number).ok().flatten() == Some(hash);
+
+            (number <= finalized && canonical).then_some(())
+        })
+        .await
+    }
+
+    /// Wait until the block with `hash` has been imported, return its number
+    pub async fn wait_for_import(
+        &self,
+        hash: B::Hash,
+        timeout: Duration,
+    ) -> Result<NumberFor<B>, WaitError<B>> {
+        self.wait(format!("imported block {:?}", hash), timeout, || {
+            self.inner.number(hash).ok().flatten()
+        })
+        .await
+    }
+
+    /// Wait until `condition` holds for the client blockchain info, return that info
+    pub async fn wait_until<F>(
+        &self,
+        condition: F,
+        timeout: Duration,
+    ) -> Result<Info<B>, WaitError<B>>
+    where
+        F: Fn(&Info<B>) -> bool,
+    {
+        self.wait("chain condition".to_string(), timeout, || {
+            let info = self.info();
+            condition(&info).then_some(info)
+        })
+        .await
+    }
+
+    // Evaluate `check` initially and after each import or finality notification
+    async fn wait<T, F>(
+        &self,
+        condition: String,
+        timeout: Duration,
+        check: F,
+    ) -> Result<T, WaitError<B>>
+    where
+        F: Fn() -> Option<T>,
+    {
+        // subscribe before the first check, so no notification gets lost
+        let mut imports = self.inner.import_notification_stream();
+        let mut finality = self.inner.finality_notification_stream();
+
+        let wait = async {
+            loop {
+                if let Some(res) = check() {
+                    return Some(res);
+                }
+
+                let closed = futures::select! {
+                    n = imports.next().fuse() => n.is_none(),
+                    n = finality.next().fuse() => n.is_none(),
+                };
+
+                if closed {
+                    return None;
+                }
+            }
+        };
+
+        let leaves = || self.backend.blockchain().leaves().unwrap_or_default();
+
+        match tokio::time::timeout(timeout, wait).await {
+            Ok(Some(res)) => Ok(res),
+            Ok(None) => Err(WaitError::Closed {
+                condition,
+                info: self.info(),
+                leaves: leaves(),
+            }),
+            Err(_) => Err(WaitError::Timeout {
+                condition,
+                info: self.info(),
+                leaves: leaves(),
+            }),
+        }
+    }
+}
@@ -0,0 +1,146 @@
+// Copyright (C) 2021 Andreas Doerr
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use std::time::Duration;
+
+use sp_runtime::generic::BlockId;
+
+use super::WaitError;
+use crate::{BlockTree, Client, TransactionPool};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[tokio::test]
+async fn wait_for_best() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let pool = TransactionPool::new(client.clone());
+
+    let author = async {
+        let mut hash = Default::default();
+
+        for _ in 0..3 {
+            tokio::task::yield_now().await;
+            hash = pool.author_block(None).await;
+        }
+
+        hash
+    };
+
+    let (best, authored) = tokio::join!(client.wait_for_best(3, TIMEOUT), author);
+
+    assert_eq!(authored, best.unwrap());
+}
+
+#[tokio::test]
+async fn wait_for_import() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let pool = TransactionPool::new(client.clone());
+
+    let hash = pool.author_block(None).await;
+
+    // already imported blocks are reported immediately
+    assert_eq!(1, client.wait_for_import(hash, TIMEOUT).await.unwrap());
+
+    let err = client
+        .wait_for_import(Default::default(), Duration::from_millis(10))
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, WaitError::Timeout { .. }));
+}
+
+#[tokio::test]
+async fn wait_for_finalized() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let labels = BlockTree::parse("G-A-B-C; A-X")
+        .unwrap()
+        .build_client(&client)
+        .await;
+
+    let finalize = async {
+        tokio::task::yield_now().await;
+        client
+            .finalize_block(BlockId::Hash(labels["C"]), None, true)
+            .unwrap();
+    };
+
+    // `B` gets finalized implicitly
+    let (res, _) = tokio::join!(client.wait_for_finalized(labels["B"], TIMEOUT), finalize);
+
+    assert!(res.is_ok());
+
+    // `X` is not on the finalized chain
+    let err = client
+        .wait_for_finalized(labels["X"], Duration::from_millis(10))
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, WaitError::Timeout { .. }));
+}
+
+#[tokio::test]
+async fn wait_until() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let labels = BlockTree::parse("G-A-B-C!")
+        .unwrap()
+        .build_client(&client)
+        .await;
+
+    let info = client
+        .wait_until(|info| info.finalized_number == 3, TIMEOUT)
+        .await
+        .unwrap();
+
+    assert_eq!(labels["C"], info.finalized_hash);
+}
+
+#[tokio::test(start_paused = true)]
+async fn timeout_reports_chain_state() {
+    sp_tracing::try_init_simple();
+
+    let client = Client::new();
+    let labels = BlockTree::parse("G-A!-B; A-C")
+        .unwrap()
+        .build_client(&client)
+        .await;
+
+    let err = client
+        .wait_until(|info| info.best_number == 10, TIMEOUT)
+        .await
+        .unwrap_err();
+
+    let WaitError::Timeout { info, leaves, .. } = &err else {
+        panic!("unexpected error: {}", err);
+    };
+
+    assert_eq!(2, info.best_number);
+    assert_eq!(labels["A"], info.finalized_hash);
+    assert_eq!(2, leaves.len());
+
+    let msg = err.to_string();
+
+    assert!(msg.starts_with("timeout waiting for chain condition"));
+    assert!(msg.contains("best #2"));
+    assert!(msg.contains("finalized #1"));
+}
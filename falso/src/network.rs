@@ -316,7 +316,7 @@ impl NetworkProvider for Network {
     ) {
         (
             client.as_block_import(),
-            Some(Box::new(Finalizer(client))),
+            Some(Box::new(Finalizer::new(client))),
             Default::default(),
         )
     }
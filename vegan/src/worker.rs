@@ -16,7 +16,10 @@
 
 #![allow(dead_code)]
 
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
 
 use futures::{FutureExt, StreamExt};
 use sc_client_api::{Backend, BlockchainEvents, FinalityNotifications, Finalizer};
@@ -66,6 +69,8 @@ where
     client: Arc<C>,
     backend: Arc<BE>,
     finality_notifications: FinalityNotifications<B>,
+    /// Number of finality notifications processed
+    processed: Arc<AtomicUsize>,
 }
 
 impl<B, BE, C> Worker<B, BE, C>
@@ -81,6 +86,7 @@ where
             client: client.clone(),
             backend,
             finality_notifications: client.finality_notification_stream(),
+            processed: Arc::new(AtomicUsize::new(0)),
         }
     }
 
@@ -93,6 +99,7 @@ where
                 notification = self.finality_notifications.next().fuse() => {
                     if let Some(notification) = notification {
                         debug!(target: "vegan", "🥬 Finality notificaton: {:?}", notification);
+                        self.processed.fetch_add(1, Ordering::SeqCst);
                     } else {
                         debug!(target: "vegan", "🥬 Finality notification stream closed!");
                         return;
@@ -14,7 +14,10 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+use std::{sync::atomic::Ordering, time::Duration};
+
 use falso::{Network, NetworkProvider, PeerConfig};
+use sp_runtime::generic::BlockId;
 use tokio::{task, time};
 
 use super::{Worker, WorkerParams};
@@ -34,17 +37,30 @@ async fn idle_worker() {
         backend: peer.client().as_backend(),
     };
 
-    let worker = task::spawn(async {
-        let mut worker = Worker::new(params);
+    let mut worker = Worker::new(params);
+    let processed = worker.processed.clone();
+
+    let worker = task::spawn(async move {
         let _ = worker.run().await;
     });
 
-    peer.add_blocks(5);
+    let client = peer.client();
+    let hash = peer.add_blocks(5);
 
     net.block_until_synced();
 
-    // give the worker a chance to acutally run
-    time::sleep(time::Duration::from_millis(50)).await;
+    client
+        .finalize_block(BlockId::Hash(hash), None, true)
+        .unwrap();
+
+    // yield until the worker has processed the finality notification
+    time::timeout(Duration::from_secs(5), async {
+        while processed.load(Ordering::SeqCst) == 0 {
+            task::yield_now().await;
+        }
+    })
+    .await
+    .expect("finality notification not processed");
 
     worker.abort();
     assert!(worker.await.unwrap_err().is_cancelled());